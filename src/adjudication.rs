@@ -0,0 +1,215 @@
+//! Adjudication of long repetitions under the Asian (AXF) rules. 
+//! 
+//! When a position comes back for the third time, every move of the repeating cycle is 
+//! classified. A side which only checks (长将) or only checks and chases (长捉) loses, 
+//! 长将 being graver than 长捉 when both sides do so; any other repetition is a draw. 
+
+use crate::square::{Move, Square};
+use crate::xiangqi::{ChessPiece, ChessType, is_checked, legal_operators};
+use crate::{ChessInfo, GameController, GameState};
+
+/// The position has to occur this many times before the repetition is adjudicated. 
+pub const REPETITIONS: usize = 3; 
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MoveKind {
+    /// The move gives check. 
+    Check, 
+    /// The move creates a new threat on an unprotected piece, or on a chariot. 
+    Chase, 
+    /// Anything else. 
+    Idle, 
+}
+
+/// Classify the move `m`, with `after` being the board right after it. 
+/// 
+/// A chase may come from any chess of the moving camp, so a move unmasking a chariot or a 
+/// cannon chases too: the squares threatened by the whole camp are compared before and after. 
+pub fn classify(after: &[ChessPiece; 90], m: &Move) -> MoveKind {
+    // a face-down chess has shown its real type by now. 
+    let black = match after[m.to.index()] {
+        ChessPiece::Chess { chess_type: _, black, .. } => black, 
+        ChessPiece::None => return MoveKind::Idle, 
+    }; 
+    if is_checked(after, !black) {
+        return MoveKind::Check; 
+    }
+    let mut before = *after; 
+    m.revert(&mut before); 
+    let threatened_before: Vec<Square> = threats(&before, black).into_iter()
+        .map(|(_, victim)| victim)
+        .collect(); 
+    for (attacker, victim) in threats(after, black) {
+        if threatened_before.contains(&victim) {
+            continue; 
+        }
+        let (attacker_type, victim_type) = match (after[attacker.index()], after[victim.index()]) {
+            (ChessPiece::Chess { chess_type: a, .. }, ChessPiece::Chess { chess_type: v, .. }) => (a, v), 
+            _ => continue, 
+        }; 
+        let chased = match victim_type {
+            ChessType::JIANG => false, 
+            // a soldier is only worth chasing once it has crossed the river. 
+            ChessType::BING if !victim.crossed(!black) => false, 
+            ChessType::CHE if attacker_type != ChessType::CHE => true, 
+            _ => !protected(after, attacker, victim), 
+        }; 
+        if chased {
+            return MoveKind::Chase; 
+        }
+    }
+    MoveKind::Idle 
+}
+
+/// The `(attacker, victim)` pairs of the chesses of the camp `black` threatening a chess of 
+/// the other camp. The general and the soldiers may threaten pieces as often as they like, 
+/// so they are left out. 
+fn threats(chesses: &[ChessPiece; 90], black: bool) -> Vec<(Square, Square)> {
+    let mut result = Vec::new(); 
+    for attacker in Square::all() {
+        match chesses[attacker.index()] {
+            ChessPiece::Chess { chess_type, black: chess_camp, .. } 
+                if chess_camp == black && chess_type != ChessType::JIANG && chess_type != ChessType::BING => {}
+            _ => continue, 
+        }
+        for victim in legal_operators(chesses, attacker).unwrap_or_default() {
+            if let ChessPiece::Chess { .. } = chesses[victim.index()] {
+                result.push((attacker, victim)); 
+            }
+        }
+    }
+    result 
+}
+
+/// Whether the chess on `victim` could be recaptured after the chess on `attacker` takes it. 
+fn protected(chesses: &[ChessPiece; 90], attacker: Square, victim: Square) -> bool {
+    let black = match chesses[victim.index()] {
+        ChessPiece::Chess { chess_type: _, black, .. } => black, 
+        ChessPiece::None => return false, 
+    }; 
+    let mut next = *chesses; 
+    Move::new(chesses, attacker, victim).apply(&mut next); 
+    Square::all().any(|square| match next[square.index()] {
+        ChessPiece::Chess { chess_type: _, black: chess_camp, .. } if chess_camp == black => {
+            legal_operators(&next, square).is_ok_and(|targets| targets.contains(&victim))
+        }
+        _ => false, 
+    })
+}
+
+/// Adjudicate the game if its current position has repeated [`REPETITIONS`] times. 
+/// 
+/// The repeating cycle is made of the operators played since the position occurred last time. 
+pub fn adjudicate(game: &GameController, chess: &ChessInfo) -> Option<GameState> {
+    if game.repetitions(chess) < REPETITIONS {
+        return None; 
+    }
+    let current = chess.hash(); 
+    let start = (0..game.operators.len()).rev()
+        .find(|&i| game.operators[i].hash == current)?; 
+
+    // walk back from the current board, classifying each operator of the cycle. 
+    // index 0 collects the moves of the red camp, 1 of the black one: a camp keeps the mildest 
+    // kind among its moves, so it stays at Check only if every move of it checks. 
+    let mut cycle = [MoveKind::Check, MoveKind::Check]; 
+    let mut board = *chess.info; 
+    for m in game.operators[start..].iter().rev() {
+        let m = m.movement(); 
+        let black = match m.piece {
+            ChessPiece::Chess { chess_type: _, black, .. } => black, 
+            ChessPiece::None => continue, 
+        }; 
+        let kind = classify(&board, m); 
+        if severity(kind) < severity(cycle[black as usize]) {
+            cycle[black as usize] = kind; 
+        }
+        m.revert(&mut board); 
+    }
+    Some(verdict(cycle)) 
+}
+
+/// How grave a kind of move is when a camp repeats only that: 长将 is worse than 长捉, 
+/// idle moves are allowed. 
+fn severity(kind: MoveKind) -> u8 {
+    match kind {
+        MoveKind::Check => 2, 
+        MoveKind::Chase => 1, 
+        MoveKind::Idle => 0, 
+    }
+}
+
+/// The result of a repetition, from the kinds of the red and the black cycles: the camp with 
+/// the graver one loses, so perpetual check loses to perpetual chase, and the same kinds draw. 
+fn verdict(cycle: [MoveKind; 2]) -> GameState {
+    let [red, black] = cycle.map(severity); 
+    if red > black {
+        GameState::Win { black: true }
+    } else if black > red {
+        GameState::Win { black: false }
+    } else {
+        GameState::Draw 
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*; 
+    use crate::notation::parse_iccs; 
+
+    /// Play the moves round and round from the FEN until the game ends. 
+    fn repeat(fen: &str, moves: &[&str]) -> GameState {
+        let mut chess = ChessInfo::from_fen(fen).unwrap(); 
+        let mut game = GameController::new(); 
+        for text in moves.iter().cycle().take(moves.len() * REPETITIONS) {
+            if !matches!(game.state, GameState::Playing(_)) {
+                break; 
+            }
+            let m = parse_iccs(&chess, text).unwrap_or_else(|e| panic!("{text}: {e}")); 
+            game.play(&mut chess, m); 
+        }
+        game.state 
+    }
+
+    #[test]
+    fn perpetual_check_loses() {
+        let state = repeat("4k4/9/9/9/9/9/9/9/9/R2K5 w", &["a0a9", "e9e8", "a9a0", "e8e9"]); 
+        // checking only every other move is no 长将. 
+        assert!(matches!(state, GameState::Draw)); 
+        let state = repeat("R3k4/9/9/9/9/9/9/9/9/3K5 b", &["e9e8", "a9a8", "e8e9", "a8a9"]); 
+        assert!(matches!(state, GameState::Win { black: true })); 
+    }
+
+    #[test]
+    fn perpetual_chase_loses() {
+        let state = repeat("4k4/9/R1c6/9/9/9/9/9/9/3K5 b", &["c7c5", "a7a5", "c5c7", "a5a7"]); 
+        assert!(matches!(state, GameState::Win { black: true })); 
+    }
+
+    #[test]
+    fn idle_repetition_draws() {
+        let state = repeat("4k4/9/9/9/9/9/9/9/9/3K5 w", &["d0d1", "e9e8", "d1d0", "e8e9"]); 
+        assert!(matches!(state, GameState::Draw)); 
+    }
+
+    #[test]
+    fn discovered_chase() {
+        // the horse steps aside and unmasks the chariot onto the lone cannon. 
+        let mut chess = ChessInfo::from_fen("4k4/9/RNc6/9/9/9/9/9/9/3K5 w").unwrap(); 
+        let m = parse_iccs(&chess, "b7a5").unwrap(); 
+        chess.make_move(m); 
+        assert_eq!(classify(chess.chesses(), &m), MoveKind::Chase); 
+        chess.set_side_to_move(false); 
+        let m = parse_iccs(&chess, "a5b7").unwrap(); 
+        chess.make_move(m); 
+        assert_eq!(classify(chess.chesses(), &m), MoveKind::Idle); 
+    }
+
+    #[test]
+    fn check_is_graver_than_chase() {
+        assert!(matches!(verdict([MoveKind::Check, MoveKind::Chase]), GameState::Win { black: true })); 
+        assert!(matches!(verdict([MoveKind::Chase, MoveKind::Check]), GameState::Win { black: false })); 
+        assert!(matches!(verdict([MoveKind::Idle, MoveKind::Chase]), GameState::Win { black: false })); 
+        assert!(matches!(verdict([MoveKind::Check, MoveKind::Check]), GameState::Draw)); 
+        assert!(matches!(verdict([MoveKind::Idle, MoveKind::Idle]), GameState::Draw)); 
+    }
+}
@@ -0,0 +1,377 @@
+//! Banqi (暗棋): the 32 chesses of xiangqi shuffled face-down on a 4x8 half board.
+//!
+//! A move either turns a face-down chess up, or steps a face-up chess of the own camp one
+//! position orthogonally. A chess captures the ones of its rank or lower; the soldier captures
+//! the general but not the other way around, and the cannon captures anything by jumping
+//! exactly one chess. A camp which has no chess left or can't move any more has lost.
+//!
+//! The first player has no camp until they turn the first chess up: they play its camp, so the
+//! other camp moves next.
+
+use crate::chess::{ChessBoard, Outcome};
+use crate::utils;
+use crate::xiangqi::{ChessPiece, ChessType, Hidden};
+
+pub const ROWS: usize = 4;
+pub const COLS: usize = 8;
+
+/// Plies without a capture or a turned chess before the game is drawn.
+pub const QUIET_LIMIT: usize = 100;
+
+const ORTHOGONAL: [(i32, i32); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+
+/// A move between positions `row * 8 + col`; a move onto its own position turns the chess up.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BanqiMove {
+    pub from: usize,
+    pub to: usize,
+}
+
+impl BanqiMove {
+    pub fn is_flip(&self) -> bool {
+        self.from == self.to
+    }
+}
+
+/// What a played move needs to be taken back.
+#[derive(Clone, Copy, Debug)]
+struct Played {
+    m: BanqiMove,
+    /// The chess on `to` before the move; the face-down one itself for a flip.
+    before: ChessPiece,
+    quiet: usize,
+}
+
+/// The rank deciding which chess may capture which, the general highest.
+fn rank(chess_type: ChessType) -> u8 {
+    match chess_type {
+        ChessType::JIANG => 7,
+        ChessType::SHI => 6,
+        ChessType::XIANG => 5,
+        ChessType::CHE => 4,
+        ChessType::MA => 3,
+        ChessType::PAO => 2,
+        ChessType::BING => 1,
+    }
+}
+
+/// Whether a chess of `attacker` type may capture one of `victim` type by an ordinary step.
+fn can_capture(attacker: ChessType, victim: ChessType) -> bool {
+    match (attacker, victim) {
+        (ChessType::BING, ChessType::JIANG) => true,
+        (ChessType::JIANG, ChessType::BING) => false,
+        // the cannon only captures by jumping.
+        (ChessType::PAO, _) => false,
+        _ => rank(attacker) >= rank(victim),
+    }
+}
+
+fn offset(index: usize, d_row: i32, d_col: i32) -> Option<usize> {
+    let row = (index / COLS) as i32 + d_row;
+    let col = (index % COLS) as i32 + d_col;
+    if (0..ROWS as i32).contains(&row) && (0..COLS as i32).contains(&col) {
+        Some(row as usize * COLS + col as usize)
+    } else {
+        None
+    }
+}
+
+pub struct Banqi {
+    board: [ChessPiece; ROWS * COLS],
+    /// The camp to move; before the first chess is turned up the players have no camp yet, and
+    /// the first one counts as red (`black: false`).
+    black: bool,
+    /// Plies since the last capture or turned chess.
+    quiet: usize,
+    history: Vec<Played>,
+}
+
+impl Banqi {
+    /// A new game with the 32 chesses dealt face-down at random by `seed`.
+    pub fn new(mut seed: u64) -> Self {
+        use ChessType::*;
+        let set = [JIANG, SHI, SHI, XIANG, XIANG, MA, MA, CHE, CHE, PAO, PAO, BING, BING, BING, BING, BING];
+        let mut chesses = Vec::new();
+        for black in [false, true] {
+            for chess_type in set {
+                chesses.push(ChessPiece::Chess { chess_type, black, hidden: Hidden::FaceDown(chess_type) });
+            }
+        }
+        utils::shuffle(&mut chesses, &mut seed);
+        let mut board = [ChessPiece::None; ROWS * COLS];
+        board.copy_from_slice(&chesses);
+        Banqi {
+            board,
+            black: false,
+            quiet: 0,
+            history: Vec::new(),
+        }
+    }
+
+    pub fn get(&self, index: usize) -> ChessPiece {
+        self.board[index]
+    }
+
+    /// The moves of the camp to move, whether the game is over or not.
+    fn moves(&self) -> Vec<BanqiMove> {
+        let mut result = Vec::new();
+        for from in 0..ROWS * COLS {
+            let chess_type = match self.board[from] {
+                ChessPiece::Chess { chess_type: _, black: _, hidden: Hidden::FaceDown(_) } => {
+                    result.push(BanqiMove { from, to: from });
+                    continue;
+                }
+                ChessPiece::Chess { chess_type, black, .. } if black == self.black => chess_type,
+                _ => continue,
+            };
+            for (d_row, d_col) in ORTHOGONAL {
+                if let Some(to) = offset(from, d_row, d_col) {
+                    let allowed = match self.board[to] {
+                        ChessPiece::None => true,
+                        ChessPiece::Chess { chess_type: _, black: _, hidden: Hidden::FaceDown(_) } => false,
+                        ChessPiece::Chess { chess_type: victim, black, .. } => {
+                            black != self.black && can_capture(chess_type, victim)
+                        }
+                    };
+                    if allowed {
+                        result.push(BanqiMove { from, to });
+                    }
+                }
+                if chess_type == ChessType::PAO {
+                    if let Some(to) = self.jump(from, d_row, d_col) {
+                        result.push(BanqiMove { from, to });
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    /// The enemy the cannon on `from` captures in the given direction, over exactly one chess.
+    fn jump(&self, from: usize, d_row: i32, d_col: i32) -> Option<usize> {
+        let mut screens = 0;
+        let mut index = from;
+        while let Some(next) = offset(index, d_row, d_col) {
+            index = next;
+            match self.board[index] {
+                ChessPiece::None => continue,
+                _ if screens == 0 => screens += 1,
+                ChessPiece::Chess { chess_type: _, black, hidden } => {
+                    let enemy = black != self.black && !matches!(hidden, Hidden::FaceDown(_));
+                    return if enemy { Some(index) } else { None };
+                }
+            }
+        }
+        None
+    }
+
+    /// How many chesses of the camp are still on the board, face-down ones included.
+    pub fn remaining(&self, black: bool) -> usize {
+        self.board.iter()
+            .filter(|piece| matches!(piece, ChessPiece::Chess { chess_type: _, black: camp, .. } if *camp == black))
+            .count()
+    }
+}
+
+impl ChessBoard for Banqi {
+    type Piece = ChessPiece;
+    type Move = BanqiMove;
+
+    fn size(&self) -> (usize, usize) {
+        (COLS, ROWS)
+    }
+
+    fn piece(&self, row: usize, col: usize) -> Option<ChessPiece> {
+        if row >= ROWS || col >= COLS {
+            return None;
+        }
+        match self.board[row * COLS + col] {
+            ChessPiece::None => None,
+            piece => Some(piece),
+        }
+    }
+
+    fn glyph(&self, piece: ChessPiece) -> (&'static str, Option<bool>) {
+        match piece {
+            // the camp of a face-down chess is unknown too.
+            ChessPiece::Chess { chess_type: _, black: _, hidden: Hidden::FaceDown(_) } => (piece.name(), None),
+            ChessPiece::Chess { black, .. } => (piece.name(), Some(black)),
+            ChessPiece::None => ("", None),
+        }
+    }
+
+    fn legal_moves(&self) -> Vec<BanqiMove> {
+        match self.result() {
+            Some(_) => Vec::new(),
+            None => self.moves(),
+        }
+    }
+
+    fn endpoints(&self, m: &BanqiMove) -> ((usize, usize), (usize, usize)) {
+        ((m.from / COLS, m.from % COLS), (m.to / COLS, m.to % COLS))
+    }
+
+    fn make_move(&mut self, m: BanqiMove) {
+        let before = self.board[m.to];
+        self.history.push(Played { m, before, quiet: self.quiet });
+        if m.is_flip() {
+            self.board[m.to] = before.revealed();
+            self.quiet = 0;
+        } else {
+            self.quiet = match before {
+                ChessPiece::None => self.quiet + 1,
+                _ => 0,
+            };
+            self.board[m.to] = self.board[m.from];
+            self.board[m.from] = ChessPiece::None;
+        }
+        self.black = match (self.history.len(), self.board[m.to]) {
+            // the first chess turned up decides the camp of the first player.
+            (1, ChessPiece::Chess { chess_type: _, black, .. }) => !black,
+            _ => !self.black,
+        };
+    }
+
+    fn undo_move(&mut self) -> Option<BanqiMove> {
+        let played = self.history.pop()?;
+        let m = played.m;
+        if !m.is_flip() {
+            self.board[m.from] = self.board[m.to];
+        }
+        self.board[m.to] = played.before;
+        self.quiet = played.quiet;
+        self.black = match self.history.len() {
+            0 => false,
+            _ => !self.black,
+        };
+        Some(m)
+    }
+
+    fn side_to_move(&self) -> bool {
+        self.black
+    }
+
+    fn result(&self) -> Option<Outcome> {
+        if self.remaining(self.black) == 0 || self.moves().is_empty() {
+            return Some(Outcome::Win { black: !self.black });
+        }
+        if self.quiet >= QUIET_LIMIT {
+            return Some(Outcome::Draw);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A board holding the face-up chesses `(position, type, camp)`, the camp `black` to move.
+    fn position(chesses: &[(usize, ChessType, bool)], black: bool) -> Banqi {
+        let mut board = [ChessPiece::None; ROWS * COLS];
+        for &(index, chess_type, camp) in chesses {
+            board[index] = ChessPiece::Chess { chess_type, black: camp, hidden: Hidden::Revealed };
+        }
+        Banqi { board, black, quiet: 0, history: Vec::new() }
+    }
+
+    fn targets(game: &Banqi, from: usize) -> Vec<usize> {
+        let mut result: Vec<usize> = game.legal_moves().into_iter()
+            .filter(|m| m.from == from && !m.is_flip())
+            .map(|m| m.to)
+            .collect();
+        result.sort();
+        result
+    }
+
+    #[test]
+    fn first_flip_decides_the_camps() {
+        for seed in 0..20 {
+            let mut game = Banqi::new(seed);
+            let first = game.legal_moves()[0];
+            game.make_move(first);
+            let flipped = match game.get(first.to) {
+                ChessPiece::Chess { chess_type: _, black, .. } => black,
+                ChessPiece::None => unreachable!(),
+            };
+            // the first player took the camp of the flipped chess, the other one moves.
+            assert_eq!(game.side_to_move(), !flipped);
+            assert!(game.legal_moves().iter().all(|m| m.is_flip() || matches!(
+                game.get(m.from),
+                ChessPiece::Chess { chess_type: _, black, .. } if black != flipped
+            )));
+            game.make_move(game.legal_moves()[0]);
+            assert_eq!(game.side_to_move(), flipped);
+            game.undo_move();
+            game.undo_move();
+            assert!(!game.side_to_move());
+        }
+    }
+
+    #[test]
+    fn ranks() {
+        use ChessType::*;
+        // the soldier takes the general, the general can't take the soldier.
+        let game = position(&[(0, BING, false), (1, JIANG, true), (9, SHI, true)], false);
+        assert_eq!(targets(&game, 0), [1, 8]);
+        let game = position(&[(0, BING, false), (1, JIANG, true), (9, SHI, true)], true);
+        assert_eq!(targets(&game, 1), [2]);
+        // higher ranks take lower ones and their equals, not the other way.
+        let game = position(&[(0, CHE, false), (1, MA, true), (8, CHE, true), (24, JIANG, false)], false);
+        assert_eq!(targets(&game, 0), [1, 8]);
+        let game = position(&[(0, CHE, false), (1, MA, true), (8, CHE, true), (24, JIANG, false)], true);
+        assert_eq!(targets(&game, 1), [2, 9]);
+    }
+
+    #[test]
+    fn cannon_jumps() {
+        use ChessType::*;
+        // over exactly one chess, at any distance, face-down screens included.
+        let mut game = position(&[(0, PAO, false), (3, BING, false), (7, JIANG, true)], false);
+        assert_eq!(targets(&game, 0), [1, 7, 8]);
+        game.board[3] = ChessPiece::Chess { chess_type: BING, black: true, hidden: Hidden::FaceDown(BING) };
+        assert_eq!(targets(&game, 0), [1, 7, 8]);
+        // never by a step, not over two chesses, and not onto an own one.
+        let game = position(&[(0, PAO, false), (1, BING, true), (31, JIANG, true)], false);
+        assert_eq!(targets(&game, 0), [8]);
+        let game = position(&[(0, PAO, false), (2, BING, true), (3, BING, false), (7, JIANG, true)], false);
+        assert_eq!(targets(&game, 0), [1, 8]);
+        let game = position(&[(0, PAO, false), (3, BING, true), (7, JIANG, false), (31, JIANG, true)], false);
+        assert_eq!(targets(&game, 0), [1, 8]);
+    }
+
+    #[test]
+    fn flip() {
+        let mut game = Banqi::new(3);
+        let m = BanqiMove { from: 10, to: 10 };
+        assert!(game.legal_moves().contains(&m));
+        let before = game.get(10);
+        game.make_move(m);
+        match (before, game.get(10)) {
+            (
+                ChessPiece::Chess { chess_type: _, black, hidden: Hidden::FaceDown(real) },
+                ChessPiece::Chess { chess_type, black: camp, hidden },
+            ) => {
+                assert_eq!((chess_type, camp), (real, black));
+                assert!(!matches!(hidden, Hidden::FaceDown(_)));
+            }
+            other => panic!("{other:?}"),
+        }
+        game.undo_move();
+        assert!(matches!(game.get(10), ChessPiece::Chess { chess_type: _, black: _, hidden: Hidden::FaceDown(_) }));
+    }
+
+    #[test]
+    fn losses() {
+        use ChessType::*;
+        // no chess left.
+        let game = position(&[(0, BING, false)], true);
+        assert_eq!(game.result(), Some(Outcome::Win { black: false }));
+        // a soldier walled in by chariots it can't take.
+        let game = position(&[(0, BING, true), (1, CHE, false), (8, CHE, false)], true);
+        assert!(game.legal_moves().is_empty());
+        assert_eq!(game.result(), Some(Outcome::Win { black: false }));
+        let game = position(&[(0, BING, true), (1, CHE, false), (8, CHE, false)], false);
+        assert_eq!(game.result(), None);
+    }
+}
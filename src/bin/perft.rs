@@ -0,0 +1,54 @@
+//! `perft <depth> [divide] [position]`: count the leaf nodes of a reference position, 
+//! `perft verify <depth>`: check every reference position up to the depth. 
+
+use chess::perft::{divide, perft, references, verify};
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect(); 
+    if args.first().map(String::as_str) == Some("verify") {
+        let depth = args.get(1).and_then(|d| d.parse().ok()).unwrap_or(3); 
+        let problems = verify(depth); 
+        for problem in problems.iter() {
+            println!("{problem}"); 
+        }
+        if problems.is_empty() {
+            println!("all reference positions agree up to depth {depth}"); 
+        } else {
+            std::process::exit(1); 
+        }
+        return ; 
+    }
+    let depth = match args.first().and_then(|d| d.parse().ok()) {
+        Some(depth) => depth, 
+        None => {
+            eprintln!("usage: perft <depth> [divide] [position] | perft verify <depth>"); 
+            std::process::exit(2); 
+        }
+    }; 
+    let divided = args.iter().any(|a| a == "divide"); 
+    let name = args.iter().skip(1).filter(|a| *a != "divide").cloned().collect::<Vec<_>>().join(" "); 
+    let reference = references().into_iter().find(|r| name.is_empty() || r.name == name); 
+    let reference = match reference {
+        Some(reference) => reference, 
+        None => {
+            eprintln!("unknown position: {name}"); 
+            std::process::exit(2); 
+        }
+    }; 
+    let chesses = reference.chesses(); 
+    let result = match divided {
+        true => divide(&chesses, reference.black, depth).map(|counts| {
+            let mut total = 0; 
+            for (m, count) in counts {
+                println!("{}{}: {count}", m.from, m.to); 
+                total += count; 
+            }
+            println!("total: {total}"); 
+        }), 
+        false => perft(&chesses, reference.black, depth).map(|count| println!("{count}")), 
+    }; 
+    if let Err(e) = result {
+        eprintln!("{e}"); 
+        std::process::exit(1); 
+    }
+}
@@ -0,0 +1,469 @@
+//! A bitboard representation of the 9x10 board: bit `row * 9 + col` of a `u128` for every position.
+//!
+//! The steps of the general, advisor, elephant, horse and soldier are precomputed at compile time,
+//! the chariot and the cannon slide along precomputed rays.
+
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, Not};
+
+use crate::square::{Move, Square};
+use crate::xiangqi::{ChessPiece, ChessType, Hidden, possible_square};
+
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct Bitboard(pub u128);
+
+impl Bitboard {
+    pub const EMPTY: Bitboard = Bitboard(0);
+    pub const FULL: Bitboard = Bitboard((1 << 90) - 1);
+
+    pub const fn square(square: Square) -> Self {
+        Bitboard(1 << square.index())
+    }
+
+    pub const fn contains(self, square: Square) -> bool {
+        self.0 >> square.index() & 1 == 1
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub const fn count(self) -> u32 {
+        self.0.count_ones()
+    }
+
+    pub fn lowest(self) -> Option<Square> {
+        if self.0 == 0 {
+            None
+        } else {
+            Square::from_index(self.0.trailing_zeros() as usize)
+        }
+    }
+
+    pub fn highest(self) -> Option<Square> {
+        if self.0 == 0 {
+            None
+        } else {
+            Square::from_index(127 - self.0.leading_zeros() as usize)
+        }
+    }
+
+    pub fn iter(self) -> Squares {
+        Squares(self.0)
+    }
+}
+
+impl BitAnd for Bitboard {
+    type Output = Bitboard;
+    fn bitand(self, rhs: Self) -> Self {
+        Bitboard(self.0 & rhs.0)
+    }
+}
+
+impl BitOr for Bitboard {
+    type Output = Bitboard;
+    fn bitor(self, rhs: Self) -> Self {
+        Bitboard(self.0 | rhs.0)
+    }
+}
+
+impl Not for Bitboard {
+    type Output = Bitboard;
+    fn not(self) -> Self {
+        Bitboard(!self.0 & Bitboard::FULL.0)
+    }
+}
+
+impl BitAndAssign for Bitboard {
+    fn bitand_assign(&mut self, rhs: Self) {
+        self.0 &= rhs.0;
+    }
+}
+
+impl BitOrAssign for Bitboard {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// The squares of a bitboard, from the lowest index.
+pub struct Squares(u128);
+
+impl Iterator for Squares {
+    type Item = Square;
+    fn next(&mut self) -> Option<Square> {
+        if self.0 == 0 {
+            return None;
+        }
+        let index = self.0.trailing_zeros() as usize;
+        self.0 &= self.0 - 1;
+        Square::from_index(index)
+    }
+}
+
+const fn palace(row: i32, col: i32, black: bool) -> bool {
+    col >= 3 && col <= 5 && if black { row >= 7 && row <= 9 } else { row >= 0 && row <= 2 }
+}
+
+const fn own_side(row: i32, black: bool) -> bool {
+    if black { row >= 5 && row <= 9 } else { row >= 0 && row <= 4 }
+}
+
+const fn on_board(row: i32, col: i32) -> bool {
+    row >= 0 && row < 10 && col >= 0 && col < 9
+}
+
+const fn bit(row: i32, col: i32) -> u128 {
+    1 << (row * 9 + col)
+}
+
+/// Build a table of steps: `steps` are (row, col) offsets, `kind` picks the chess type
+/// whose rules decide whether a step is allowed for the camp.
+const fn steps_table(steps: &[(i32, i32)], kind: u8) -> [[Bitboard; 90]; 2] {
+    let mut table = [[Bitboard::EMPTY; 90]; 2];
+    let mut camp = 0;
+    while camp < 2 {
+        let black = camp == 1;
+        let mut index = 0;
+        while index < 90 {
+            let (row, col) = ((index / 9) as i32, (index % 9) as i32);
+            let mut bits = 0;
+            let mut s = 0;
+            while s < steps.len() {
+                let (r, c) = (row + steps[s].0, col + steps[s].1);
+                let allowed = on_board(r, c) && match kind {
+                    // 帅 / 将: inside the palace.
+                    0 => palace(row, col, black) && palace(r, c, black),
+                    // 士 / 仕: on the diagonals of the palace.
+                    1 => palace(row, col, black) && palace(r, c, black) && (col == 4) == (row == 1 || row == 8),
+                    // 相 / 象: never across the river.
+                    2 => own_side(r, black),
+                    // 马 / 馬, and the advisors and elephants of jieqi once revealed: anywhere.
+                    3 => true,
+                    // 兵 / 卒: forward, and sideways once across the river.
+                    _ => {
+                        let forward = if black { -1 } else { 1 };
+                        steps[s].0 == forward || (steps[s].0 == 0 && !own_side(row, black))
+                    }
+                };
+                if allowed {
+                    bits |= bit(r, c);
+                }
+                s += 1;
+            }
+            table[camp][index] = Bitboard(bits);
+            index += 1;
+        }
+        camp += 1;
+    }
+    table
+}
+
+const ORTHOGONAL: [(i32, i32); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+const DIAGONAL: [(i32, i32); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+const ELEPHANT: [(i32, i32); 4] = [(2, 2), (2, -2), (-2, 2), (-2, -2)];
+const HORSE: [(i32, i32); 8] = [(2, 1), (2, -1), (-2, 1), (-2, -1), (1, 2), (-1, 2), (1, -2), (-1, -2)];
+
+const JIANG_STEPS: [[Bitboard; 90]; 2] = steps_table(&ORTHOGONAL, 0);
+const SHI_STEPS: [[Bitboard; 90]; 2] = steps_table(&DIAGONAL, 1);
+const XIANG_STEPS: [[Bitboard; 90]; 2] = steps_table(&ELEPHANT, 2);
+const MA_STEPS: [[Bitboard; 90]; 2] = steps_table(&HORSE, 3);
+const BING_STEPS: [[Bitboard; 90]; 2] = steps_table(&ORTHOGONAL, 4);
+const FREE_SHI_STEPS: [[Bitboard; 90]; 2] = steps_table(&DIAGONAL, 3);
+const FREE_XIANG_STEPS: [[Bitboard; 90]; 2] = steps_table(&ELEPHANT, 3);
+
+/// The rays from every position, in the directions of `ORTHOGONAL`:
+/// up (increasing index), down, right (increasing index) and left.
+const RAYS: [[Bitboard; 90]; 4] = {
+    let mut rays = [[Bitboard::EMPTY; 90]; 4];
+    let mut d = 0;
+    while d < 4 {
+        let mut index = 0;
+        while index < 90 {
+            let (mut r, mut c) = ((index / 9) as i32, (index % 9) as i32);
+            let mut bits = 0;
+            loop {
+                r += ORTHOGONAL[d].0;
+                c += ORTHOGONAL[d].1;
+                if !on_board(r, c) {
+                    break;
+                }
+                bits |= bit(r, c);
+            }
+            rays[d][index] = Bitboard(bits);
+            index += 1;
+        }
+        d += 1;
+    }
+    rays
+};
+
+/// The first occupied square along the ray `d`.
+fn nearest(d: usize, blockers: Bitboard) -> Option<Square> {
+    match d {
+        0 | 2 => blockers.lowest(),
+        _ => blockers.highest(),
+    }
+}
+
+/// The board as one bitboard per chess type and camp.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct Board {
+    /// Indexed by `[black as usize][chess_type as usize]`.
+    pieces: [[Bitboard; 7]; 2],
+    camps: [Bitboard; 2],
+    /// The chesses of jieqi turned face up, see [`Hidden::Revealed`].
+    revealed: Bitboard,
+}
+
+impl Board {
+    pub fn new(chesses: &[ChessPiece; 90]) -> Self {
+        let mut board = Board {
+            pieces: [[Bitboard::EMPTY; 7]; 2],
+            camps: [Bitboard::EMPTY; 2],
+            revealed: Bitboard::EMPTY,
+        };
+        for square in Square::all() {
+            if let ChessPiece::Chess { chess_type, black, hidden } = chesses[square.index()] {
+                board.pieces[black as usize][chess_type as usize] |= Bitboard::square(square);
+                board.camps[black as usize] |= Bitboard::square(square);
+                if hidden == Hidden::Revealed {
+                    board.revealed |= Bitboard::square(square);
+                }
+            }
+        }
+        board
+    }
+
+    /// Put `piece` on `square`, replacing whatever stood there.
+    pub fn set(&mut self, square: Square, piece: ChessPiece) {
+        let bit = Bitboard::square(square);
+        for camp in 0..2 {
+            self.camps[camp] &= !bit;
+            for pieces in self.pieces[camp].iter_mut() {
+                *pieces &= !bit;
+            }
+        }
+        self.revealed &= !bit;
+        if let ChessPiece::Chess { chess_type, black, hidden } = piece {
+            self.pieces[black as usize][chess_type as usize] |= bit;
+            self.camps[black as usize] |= bit;
+            if hidden == Hidden::Revealed {
+                self.revealed |= bit;
+            }
+        }
+    }
+
+    /// Play a move; a face-down chess turns face up.
+    pub fn apply(&mut self, m: &Move) {
+        self.set(m.to, m.piece.revealed());
+        self.set(m.from, ChessPiece::None);
+    }
+
+    pub fn pieces(&self, chess_type: ChessType, black: bool) -> Bitboard {
+        self.pieces[black as usize][chess_type as usize]
+    }
+
+    pub fn camp(&self, black: bool) -> Bitboard {
+        self.camps[black as usize]
+    }
+
+    pub fn occupied(&self) -> Bitboard {
+        self.camps[0] | self.camps[1]
+    }
+
+    /// The chess on `square`; a face-down one is seen as the type it moves as, with its real type unknown.
+    pub fn piece(&self, square: Square) -> ChessPiece {
+        for black in [false, true] {
+            if self.camp(black).contains(square) {
+                for chess_type in ChessType::ALL {
+                    if self.pieces(chess_type, black).contains(square) {
+                        let hidden = match self.revealed.contains(square) {
+                            true => Hidden::Revealed,
+                            false => Hidden::Never,
+                        };
+                        return ChessPiece::Chess { chess_type, black, hidden };
+                    }
+                }
+            }
+        }
+        ChessPiece::None
+    }
+
+    /// The pseudo-legal targets of the chess on `square`; empty for an empty square.
+    pub fn targets(&self, square: Square) -> Bitboard {
+        let index = square.index();
+        let (chess_type, black) = match self.piece(square) {
+            ChessPiece::Chess { chess_type, black, .. } => (chess_type, black),
+            ChessPiece::None => return Bitboard::EMPTY,
+        };
+        let camp = black as usize;
+        let occupied = self.occupied();
+        let targets = match chess_type {
+            ChessType::JIANG => {
+                let mut targets = JIANG_STEPS[camp][index];
+                // the general flies straight down an open file and captures the other one.
+                let d = if black { 1 } else { 0 };
+                if let Some(other) = nearest(d, RAYS[d][index] & occupied) {
+                    if self.pieces(ChessType::JIANG, !black).contains(other) {
+                        targets |= Bitboard::square(other);
+                    }
+                }
+                targets
+            }
+            ChessType::SHI if self.revealed.contains(square) => FREE_SHI_STEPS[camp][index],
+            ChessType::SHI => SHI_STEPS[camp][index],
+            ChessType::XIANG => {
+                let steps = match self.revealed.contains(square) {
+                    true => FREE_XIANG_STEPS[camp][index],
+                    false => XIANG_STEPS[camp][index],
+                };
+                let mut targets = Bitboard::EMPTY;
+                for to in steps.iter() {
+                    let eye = Bitboard(1 << ((index + to.index()) / 2));
+                    if (occupied & eye).is_empty() {
+                        targets |= Bitboard::square(to);
+                    }
+                }
+                targets
+            }
+            ChessType::MA => {
+                let mut targets = Bitboard::EMPTY;
+                for to in MA_STEPS[camp][index].iter() {
+                    let (row, col) = (square.row(), square.col());
+                    let leg = if to.row().abs_diff(row) == 2 {
+                        (row + to.row()) / 2 * 9 + col
+                    } else {
+                        row * 9 + (col + to.col()) / 2
+                    };
+                    if (occupied & Bitboard(1 << leg)).is_empty() {
+                        targets |= Bitboard::square(to);
+                    }
+                }
+                targets
+            }
+            ChessType::BING => BING_STEPS[camp][index],
+            ChessType::CHE => {
+                let mut targets = Bitboard::EMPTY;
+                for (d, rays) in RAYS.iter().enumerate() {
+                    let ray = rays[index];
+                    targets |= match nearest(d, ray & occupied) {
+                        Some(blocker) => ray & !rays[blocker.index()],
+                        None => ray,
+                    };
+                }
+                targets
+            }
+            ChessType::PAO => {
+                let mut targets = Bitboard::EMPTY;
+                for (d, rays) in RAYS.iter().enumerate() {
+                    let ray = rays[index];
+                    match nearest(d, ray & occupied) {
+                        Some(screen) => {
+                            targets |= ray & !rays[screen.index()] & !Bitboard::square(screen);
+                            if let Some(victim) = nearest(d, rays[screen.index()] & occupied) {
+                                targets |= Bitboard::square(victim);
+                            }
+                        }
+                        None => targets |= ray,
+                    }
+                }
+                targets
+            }
+        };
+        targets & !self.camp(black)
+    }
+
+    /// Whether any chess of the camp `black` can move onto `square`.
+    pub fn attacked(&self, square: Square, black: bool) -> bool {
+        self.camp(black).iter().any(|from| self.targets(from).contains(square))
+    }
+
+    /// Whether the general of the camp can be captured right now; never without that general.
+    pub fn is_checked(&self, black: bool) -> bool {
+        match self.pieces(ChessType::JIANG, black).lowest() {
+            Some(jiang) => self.attacked(jiang, !black),
+            None => false,
+        }
+    }
+
+    /// Whether the two generals stand on one file with nothing between them.
+    pub fn generals_facing(&self) -> bool {
+        match (self.pieces(ChessType::JIANG, false).lowest(), self.pieces(ChessType::JIANG, true).lowest()) {
+            (Some(red), Some(black)) => nearest(0, RAYS[0][red.index()] & self.occupied()) == Some(black),
+            _ => false,
+        }
+    }
+
+    /// The targets of the chess on `square` which don't leave its own general in check.
+    /// A chess standing on an impossible square can't move at all.
+    pub fn legal_targets(&self, square: Square) -> Bitboard {
+        let piece = self.piece(square);
+        let black = match piece {
+            ChessPiece::Chess { chess_type, black, hidden } => {
+                if hidden != Hidden::Revealed && !possible_square(chess_type, black, square) {
+                    return Bitboard::EMPTY;
+                }
+                black
+            }
+            ChessPiece::None => return Bitboard::EMPTY,
+        };
+        let mut result = Bitboard::EMPTY;
+        for to in self.targets(square).iter() {
+            let mut next = *self;
+            next.apply(&Move { from: square, to, piece, captured: self.piece(to) });
+            if !next.generals_facing() && !next.is_checked(black) {
+                result |= Bitboard::square(to);
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::perft::references;
+    use crate::xiangqi::{calculate_operators, moves};
+
+    /// Every square of the position, then of the positions one ply later.
+    fn compare(chesses: &[ChessPiece; 90], name: &str) {
+        for square in Square::all() {
+            if let ChessPiece::None = chesses[square.index()] {
+                continue;
+            }
+            let mut bitboard = calculate_operators(chesses, square).unwrap();
+            let mut by_hand = moves::calculate(chesses, square).unwrap();
+            bitboard.sort();
+            by_hand.sort();
+            assert_eq!(bitboard, by_hand, "{name} on {square}");
+        }
+    }
+
+    #[test]
+    fn same_targets_as_the_reference_generators() {
+        for reference in references() {
+            let chesses = reference.chesses();
+            compare(&chesses, reference.name);
+            for m in crate::xiangqi::legal_moves(&chesses, reference.black) {
+                let mut next = chesses;
+                m.apply(&mut next);
+                compare(&next, reference.name);
+            }
+        }
+    }
+
+    #[test]
+    fn kept_up_to_date_by_moves() {
+        for reference in references() {
+            let chesses = reference.chesses();
+            let board = Board::new(&chesses);
+            for m in crate::xiangqi::legal_moves(&chesses, reference.black) {
+                let mut next = chesses;
+                m.apply(&mut next);
+                let mut kept = board;
+                kept.apply(&m);
+                assert_eq!(kept, Board::new(&next), "{} after {}{}", reference.name, m.from, m.to);
+            }
+        }
+    }
+}
@@ -1,4 +1,572 @@
-/// The trait to create the ChessBoard. 
-pub trait ChessBoard { 
-    
-}
\ No newline at end of file
+/// How a finished game ended.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Outcome {
+    /// `black` is the camp of the winner.
+    Win {
+        black: bool,
+    },
+    Draw,
+}
+
+/// The trait to create the ChessBoard.
+///
+/// A board is a grid of `size()` columns and rows, addressed by `(row, col)` with row 0 at the
+/// bottom line of the first camp (`black: false`). The camps follow `ChessPiece::Chess::black`.
+pub trait ChessBoard {
+    type Piece: Copy;
+    type Move: Copy;
+
+    /// `(columns, rows)` of the board.
+    fn size(&self) -> (usize, usize);
+
+    /// The chess on `(row, col)`, `None` for an empty or out of board position.
+    fn piece(&self, row: usize, col: usize) -> Option<Self::Piece>;
+
+    /// The text to draw for a chess, and its camp if it's known.
+    fn glyph(&self, piece: Self::Piece) -> (&'static str, Option<bool>);
+
+    /// Every legal move of the camp to move; empty once the game is over.
+    fn legal_moves(&self) -> Vec<Self::Move>;
+
+    /// The `(row, col)` a move starts from and ends on.
+    fn endpoints(&self, m: &Self::Move) -> ((usize, usize), (usize, usize));
+
+    /// What tells the move apart from the other legal moves with the same endpoints, like the
+    /// piece a pawn promotes to; the players pick one of them by it.
+    fn choice(&self, _m: &Self::Move) -> &'static str {
+        ""
+    }
+
+    /// Play a legal move.
+    fn make_move(&mut self, m: Self::Move);
+
+    /// Take back the last move played, if any.
+    fn undo_move(&mut self) -> Option<Self::Move>;
+
+    /// The camp to move.
+    fn side_to_move(&self) -> bool;
+
+    /// How the game ended, `None` while it goes on.
+    fn result(&self) -> Option<Outcome>;
+
+    /// The name of a camp, as shown to the players.
+    fn camp_name(&self, black: bool) -> &'static str {
+        crate::xiangqi::camp_name(black)
+    }
+}
+
+/// The pieces of international chess. 
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PieceKind {
+    Pawn, 
+    Knight, 
+    Bishop, 
+    Rook, 
+    Queen, 
+    King, 
+}
+
+/// A piece with its camp; white is `black: false` and starts on rows 0 and 1. 
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Piece {
+    pub kind: PieceKind, 
+    pub black: bool, 
+}
+
+/// A move from one square to another, squares being `row * 8 + col`. 
+/// 
+/// Castling is the move of the king two columns aside, en passant the diagonal move of the pawn. 
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ChessMove {
+    pub from: usize, 
+    pub to: usize, 
+    /// What a pawn reaching the last row turns into. 
+    pub promotion: Option<PieceKind>, 
+}
+
+/// Everything which decides the legal moves, compared for the repetitions. 
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct Position {
+    board: [Option<Piece>; 64], 
+    black: bool, 
+    /// White king side, white queen side, black king side, black queen side. 
+    castling: [bool; 4], 
+    /// The square a pawn has just skipped, only kept while it can be taken en passant. 
+    en_passant: Option<usize>, 
+}
+
+/// What a played move needs to be taken back. 
+#[derive(Clone, Copy, Debug)]
+struct Played {
+    m: ChessMove, 
+    before: Position, 
+    halfmove: usize, 
+}
+
+const KNIGHT: [(i32, i32); 8] = [(2, 1), (2, -1), (-2, 1), (-2, -1), (1, 2), (-1, 2), (1, -2), (-1, -2)];
+const KING: [(i32, i32); 8] = [(1, 0), (-1, 0), (0, 1), (0, -1), (1, 1), (1, -1), (-1, 1), (-1, -1)];
+const ROOK: [(i32, i32); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+const BISHOP: [(i32, i32); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+const PROMOTIONS: [PieceKind; 4] = [PieceKind::Queen, PieceKind::Rook, PieceKind::Bishop, PieceKind::Knight];
+
+/// The square `d_row` rows and `d_col` columns away from `square`, if it's still on the board. 
+fn offset(square: usize, d_row: i32, d_col: i32) -> Option<usize> {
+    let row = (square / 8) as i32 + d_row; 
+    let col = (square % 8) as i32 + d_col; 
+    if (0..8).contains(&row) && (0..8).contains(&col) {
+        Some((row * 8 + col) as usize)
+    } else {
+        None
+    }
+}
+
+/// International chess: castling, en passant, promotion, the 50-move rule and threefold repetition. 
+#[derive(Clone, Debug)]
+pub struct Chess {
+    position: Position, 
+    /// Plies played since the last capture or pawn move. 
+    halfmove: usize, 
+    history: Vec<Played>, 
+}
+
+impl Chess {
+    /// A board set up for the opening, white to move. 
+    pub fn new() -> Self {
+        use PieceKind::*; 
+        let mut board = [None; 64]; 
+        let back = [Rook, Knight, Bishop, Queen, King, Bishop, Knight, Rook]; 
+        for col in 0..8 {
+            board[col] = Some(Piece { kind: back[col], black: false }); 
+            board[8 + col] = Some(Piece { kind: Pawn, black: false }); 
+            board[48 + col] = Some(Piece { kind: Pawn, black: true }); 
+            board[56 + col] = Some(Piece { kind: back[col], black: true }); 
+        }
+        Chess {
+            position: Position {
+                board, 
+                black: false, 
+                castling: [true; 4], 
+                en_passant: None, 
+            }, 
+            halfmove: 0, 
+            history: Vec::new(), 
+        }
+    }
+
+    pub fn get(&self, square: usize) -> Option<Piece> {
+        self.position.board[square]
+    }
+
+    fn find_king(&self, black: bool) -> Option<usize> {
+        (0..64).find(|&s| self.get(s) == Some(Piece { kind: PieceKind::King, black }))
+    }
+
+    /// Whether any piece of the camp `black` attacks `square`. 
+    pub fn attacked(&self, square: usize, black: bool) -> bool {
+        let is = |s: Option<usize>, kinds: &[PieceKind]| match s.and_then(|s| self.get(s)) {
+            Some(piece) => piece.black == black && kinds.contains(&piece.kind), 
+            None => false, 
+        }; 
+        // a pawn attacks forward, so look backward from the square. 
+        let back = if black { 1 } else { -1 }; 
+        if is(offset(square, back, 1), &[PieceKind::Pawn]) || is(offset(square, back, -1), &[PieceKind::Pawn]) {
+            return true; 
+        }
+        if KNIGHT.iter().any(|&(r, c)| is(offset(square, r, c), &[PieceKind::Knight])) {
+            return true; 
+        }
+        if KING.iter().any(|&(r, c)| is(offset(square, r, c), &[PieceKind::King])) {
+            return true; 
+        }
+        let slides = [(&ROOK, PieceKind::Rook), (&BISHOP, PieceKind::Bishop)]; 
+        for (directions, kind) in slides {
+            for &(r, c) in directions.iter() {
+                let mut s = square; 
+                while let Some(next) = offset(s, r, c) {
+                    s = next; 
+                    if self.get(s).is_some() {
+                        if is(Some(s), &[kind, PieceKind::Queen]) {
+                            return true; 
+                        }
+                        break; 
+                    }
+                }
+            }
+        }
+        false 
+    }
+
+    /// Whether the king of the camp `black` is attacked. 
+    pub fn is_checked(&self, black: bool) -> bool {
+        match self.find_king(black) {
+            Some(king) => self.attacked(king, !black), 
+            None => false, 
+        }
+    }
+
+    /// The moves of the camp to move, without looking at its own king. 
+    fn pseudo_moves(&self) -> Vec<ChessMove> {
+        let black = self.position.black; 
+        let mut result = Vec::new(); 
+        let mut push = |from: usize, to: usize, promote: bool| {
+            if promote {
+                for kind in PROMOTIONS {
+                    result.push(ChessMove { from, to, promotion: Some(kind) }); 
+                }
+            } else {
+                result.push(ChessMove { from, to, promotion: None }); 
+            }
+        }; 
+        for from in 0..64 {
+            let kind = match self.get(from) {
+                Some(piece) if piece.black == black => piece.kind, 
+                _ => continue, 
+            }; 
+            let free = |s: usize| self.get(s).is_none(); 
+            let enemy = |s: usize| matches!(self.get(s), Some(piece) if piece.black != black); 
+            match kind {
+                PieceKind::Pawn => {
+                    let forward = if black { -1 } else { 1 }; 
+                    let start = if black { 6 } else { 1 }; 
+                    let last = if black { 0 } else { 7 }; 
+                    if let Some(to) = offset(from, forward, 0).filter(|&s| free(s)) {
+                        push(from, to, to / 8 == last); 
+                        if from / 8 == start {
+                            if let Some(to) = offset(to, forward, 0).filter(|&s| free(s)) {
+                                push(from, to, false); 
+                            }
+                        }
+                    }
+                    for side in [1, -1] {
+                        if let Some(to) = offset(from, forward, side) {
+                            if enemy(to) || self.position.en_passant == Some(to) {
+                                push(from, to, to / 8 == last); 
+                            }
+                        }
+                    }
+                }
+                PieceKind::Knight | PieceKind::King => {
+                    let steps = if kind == PieceKind::Knight { &KNIGHT } else { &KING }; 
+                    for &(r, c) in steps.iter() {
+                        if let Some(to) = offset(from, r, c).filter(|&s| free(s) || enemy(s)) {
+                            push(from, to, false); 
+                        }
+                    }
+                }
+                _ => {
+                    let directions: &[(i32, i32)] = match kind {
+                        PieceKind::Rook => &ROOK, 
+                        PieceKind::Bishop => &BISHOP, 
+                        _ => &KING, 
+                    }; 
+                    for &(r, c) in directions {
+                        let mut s = from; 
+                        while let Some(to) = offset(s, r, c) {
+                            s = to; 
+                            if free(to) {
+                                push(from, to, false); 
+                                continue; 
+                            }
+                            if enemy(to) {
+                                push(from, to, false); 
+                            }
+                            break; 
+                        }
+                    }
+                }
+            }
+        }
+        // castling: the king and the rook haven't moved, nothing stands between them, 
+        // and the king neither is in check nor passes an attacked square. 
+        let base = if black { 56 } else { 0 }; 
+        let rights = if black { [2, 3] } else { [0, 1] }; 
+        let king_side = self.position.castling[rights[0]] 
+            && (5..7).all(|c| self.get(base + c).is_none()) 
+            && (4..7).all(|c| !self.attacked(base + c, !black)); 
+        if king_side {
+            result.push(ChessMove { from: base + 4, to: base + 6, promotion: None }); 
+        }
+        let queen_side = self.position.castling[rights[1]] 
+            && (1..4).all(|c| self.get(base + c).is_none()) 
+            && (2..5).all(|c| !self.attacked(base + c, !black)); 
+        if queen_side {
+            result.push(ChessMove { from: base + 4, to: base + 2, promotion: None }); 
+        }
+        result 
+    }
+
+    /// Play a move without checking it, and without recording it. 
+    fn play(&mut self, m: ChessMove) {
+        let p = &mut self.position; 
+        let piece = match p.board[m.from] {
+            Some(piece) => piece, 
+            None => return, 
+        }; 
+        let capture = p.board[m.to].is_some(); 
+        if piece.kind == PieceKind::Pawn && p.en_passant == Some(m.to) {
+            // the taken pawn stands beside, on the row the pawn comes from. 
+            p.board[m.from / 8 * 8 + m.to % 8] = None; 
+        }
+        if piece.kind == PieceKind::King && m.from.abs_diff(m.to) == 2 {
+            let (rook_from, rook_to) = if m.to > m.from { (m.from + 3, m.from + 1) } else { (m.from - 4, m.from - 1) }; 
+            p.board[rook_to] = p.board[rook_from].take(); 
+        }
+        p.board[m.to] = match m.promotion {
+            Some(kind) => Some(Piece { kind, black: piece.black }), 
+            None => Some(piece), 
+        }; 
+        p.board[m.from] = None; 
+
+        // a move from or onto a corner, or of a king, loses the rights of castling it touches. 
+        for (right, corner) in [7, 0, 63, 56].into_iter().enumerate() {
+            if m.from == corner || m.to == corner || m.from == corner / 56 * 56 + 4 {
+                p.castling[right] = false; 
+            }
+        }
+        p.en_passant = None; 
+        if piece.kind == PieceKind::Pawn && m.from.abs_diff(m.to) == 16 {
+            let skipped = (m.from + m.to) / 2; 
+            let takers = [offset(m.to, 0, 1), offset(m.to, 0, -1)]; 
+            if takers.iter().flatten().any(|&s| p.board[s] == Some(Piece { kind: PieceKind::Pawn, black: !piece.black })) {
+                p.en_passant = Some(skipped); 
+            }
+        }
+        p.black = !p.black; 
+        self.halfmove = if capture || piece.kind == PieceKind::Pawn { 0 } else { self.halfmove + 1 }; 
+    }
+
+    /// The legal moves of the camp to move, whether the game is over or not. 
+    fn moves(&self) -> Vec<ChessMove> {
+        let black = self.position.black; 
+        self.pseudo_moves().into_iter().filter(|&m| {
+            let mut next = self.clone(); 
+            next.play(m); 
+            !next.is_checked(black)
+        }).collect()
+    }
+
+    /// How many times the current position has occurred, itself included. 
+    pub fn repetitions(&self) -> usize {
+        1 + self.history.iter().filter(|played| played.before == self.position).count()
+    }
+
+    /// Plies played since the last capture or pawn move. 
+    pub fn halfmove(&self) -> usize {
+        self.halfmove 
+    }
+}
+
+impl Default for Chess {
+    fn default() -> Self {
+        Self::new() 
+    }
+}
+
+impl ChessBoard for Chess {
+    type Piece = Piece; 
+    type Move = ChessMove; 
+
+    fn size(&self) -> (usize, usize) {
+        (8, 8)
+    }
+
+    fn piece(&self, row: usize, col: usize) -> Option<Piece> {
+        if row < 8 && col < 8 {
+            self.get(row * 8 + col)
+        } else {
+            None
+        }
+    }
+
+    fn glyph(&self, piece: Piece) -> (&'static str, Option<bool>) {
+        let name = match piece.kind {
+            PieceKind::Pawn => "兵", 
+            PieceKind::Knight => "马", 
+            PieceKind::Bishop => "象", 
+            PieceKind::Rook => "车", 
+            PieceKind::Queen => "后", 
+            PieceKind::King => "王", 
+        }; 
+        (name, Some(piece.black))
+    }
+
+    fn choice(&self, m: &ChessMove) -> &'static str {
+        match m.promotion {
+            Some(kind) => self.glyph(Piece { kind, black: false }).0, 
+            None => "", 
+        }
+    }
+
+    fn camp_name(&self, black: bool) -> &'static str {
+        match black {
+            true => "黑方", 
+            false => "白方", 
+        }
+    }
+
+    fn legal_moves(&self) -> Vec<ChessMove> {
+        match self.result() {
+            Some(_) => Vec::new(), 
+            None => self.moves(), 
+        }
+    }
+
+    fn endpoints(&self, m: &ChessMove) -> ((usize, usize), (usize, usize)) {
+        ((m.from / 8, m.from % 8), (m.to / 8, m.to % 8))
+    }
+
+    fn make_move(&mut self, m: ChessMove) {
+        let played = Played {
+            m, 
+            before: self.position, 
+            halfmove: self.halfmove, 
+        }; 
+        self.play(m); 
+        self.history.push(played); 
+    }
+
+    fn undo_move(&mut self) -> Option<ChessMove> {
+        let played = self.history.pop()?; 
+        self.position = played.before; 
+        self.halfmove = played.halfmove; 
+        Some(played.m)
+    }
+
+    fn side_to_move(&self) -> bool {
+        self.position.black 
+    }
+
+    fn result(&self) -> Option<Outcome> {
+        let black = self.position.black; 
+        if self.moves().is_empty() {
+            if self.is_checked(black) {
+                return Some(Outcome::Win { black: !black }); 
+            }
+            return Some(Outcome::Draw); 
+        }
+        if self.halfmove >= 100 || self.repetitions() >= 3 {
+            return Some(Outcome::Draw); 
+        }
+        None 
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A board from the FEN of international chess. 
+    fn from_fen(fen: &str) -> Chess {
+        let fields: Vec<&str> = fen.split_whitespace().collect(); 
+        let mut chess = Chess::new(); 
+        let p = &mut chess.position; 
+        p.board = [None; 64]; 
+        for (i, line) in fields[0].split('/').enumerate() {
+            let row = 7 - i; 
+            let mut col = 0; 
+            for c in line.chars() {
+                if let Some(empty) = c.to_digit(10) {
+                    col += empty as usize; 
+                    continue; 
+                }
+                let kind = match c.to_ascii_lowercase() {
+                    'p' => PieceKind::Pawn, 
+                    'n' => PieceKind::Knight, 
+                    'b' => PieceKind::Bishop, 
+                    'r' => PieceKind::Rook, 
+                    'q' => PieceKind::Queen, 
+                    _ => PieceKind::King, 
+                }; 
+                p.board[row * 8 + col] = Some(Piece { kind, black: c.is_ascii_lowercase() }); 
+                col += 1; 
+            }
+        }
+        p.black = fields[1] == "b"; 
+        p.castling = ['K', 'Q', 'k', 'q'].map(|c| fields[2].contains(c)); 
+        chess.halfmove = fields.get(4).map_or(0, |n| n.parse().unwrap()); 
+        chess 
+    }
+
+    fn perft(chess: &Chess, depth: u32) -> u64 {
+        let moves = chess.moves(); 
+        if depth == 1 {
+            return moves.len() as u64; 
+        }
+        moves.into_iter().map(|m| {
+            let mut next = chess.clone(); 
+            next.play(m); 
+            perft(&next, depth - 1)
+        }).sum()
+    }
+
+    /// The published counts of the chessprogramming.org perft positions. 
+    #[test]
+    fn perft_counts() {
+        let positions: [(&str, &[u64]); 5] = [
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", &[20, 400, 8902, 197281]), 
+            ("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", &[48, 2039, 97862]), 
+            ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", &[14, 191, 2812, 43238]), 
+            ("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1", &[6, 264, 9467]), 
+            ("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8", &[44, 1486, 62379]), 
+        ]; 
+        assert_eq!(from_fen(positions[0].0).position, Chess::new().position); 
+        for (fen, counts) in positions {
+            let chess = from_fen(fen); 
+            for (depth, &count) in counts.iter().enumerate() {
+                assert_eq!(perft(&chess, depth as u32 + 1), count, "{fen} at depth {}", depth + 1); 
+            }
+        }
+    }
+
+    #[test]
+    fn fifty_moves() {
+        let mut chess = from_fen("4k3/8/8/8/8/8/8/4K2R w K - 99 80"); 
+        assert_eq!(chess.result(), None); 
+        chess.make_move(ChessMove { from: 7, to: 6, promotion: None }); 
+        assert_eq!(chess.halfmove(), 100); 
+        assert_eq!(chess.result(), Some(Outcome::Draw)); 
+        assert!(chess.legal_moves().is_empty()); 
+        chess.undo_move(); 
+        assert_eq!(chess.result(), None); 
+    }
+
+    #[test]
+    fn threefold_repetition() {
+        let mut chess = Chess::new(); 
+        // Nf3 Nf6 Ng1 Ng8, twice. 
+        let knights = [(6, 21), (62, 45), (21, 6), (45, 62)]; 
+        for (i, (from, to)) in knights.iter().cycle().take(8).enumerate() {
+            assert_eq!(chess.result(), None, "after {i} plies"); 
+            chess.make_move(ChessMove { from: *from, to: *to, promotion: None }); 
+        }
+        assert_eq!(chess.repetitions(), 3); 
+        assert_eq!(chess.result(), Some(Outcome::Draw)); 
+    }
+
+    #[test]
+    fn promotion_choices() {
+        let mut chess = from_fen("8/P7/8/8/8/8/8/k6K w - - 0 1"); 
+        let promotions: Vec<ChessMove> = chess.legal_moves().into_iter().filter(|m| m.from == 48).collect(); 
+        assert_eq!(promotions.len(), 4); 
+        let choices: Vec<&str> = promotions.iter().map(|m| chess.choice(m)).collect(); 
+        assert_eq!(choices, ["后", "车", "象", "马"]); 
+        for m in promotions {
+            chess.make_move(m); 
+            assert_eq!(chess.get(56), Some(Piece { kind: m.promotion.unwrap(), black: false })); 
+            assert_eq!(chess.get(48), None); 
+            chess.undo_move(); 
+            assert_eq!(chess.get(48), Some(Piece { kind: PieceKind::Pawn, black: false })); 
+        }
+    }
+
+    #[test]
+    fn mate_and_stalemate() {
+        // fool's mate. 
+        let mut chess = Chess::new(); 
+        for (from, to) in [(13, 21), (52, 36), (14, 30), (59, 31)] {
+            chess.make_move(ChessMove { from, to, promotion: None }); 
+        }
+        assert_eq!(chess.result(), Some(Outcome::Win { black: true })); 
+        assert_eq!(from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").result(), Some(Outcome::Draw)); 
+    }
+}
@@ -0,0 +1,220 @@
+//! FEN of xiangqi positions, with the WXF/UCCI letters shared by most xiangqi tools.
+//!
+//! A FEN lists the ranks from black's bottom line (rank 9) down to red's (rank 0), each from
+//! file `a` to `i`. Red chesses are upper case, black ones lower case: `R` chariot, `N` horse,
+//! `B` elephant, `A` advisor, `K` general, `C` cannon and `P` soldier; a digit counts empty
+//! squares. The board is followed by the side to move (`w` or `r` for red, `b` for black), two
+//! `-` fields left over from international chess, the plies since the last capture and the
+//! number of the current move:
+//!
+//! ```text
+//! rnbakabnr/9/1c5c1/p1p1p1p1p/9/9/P1P1P1P1P/1C5C1/9/RNBAKABNR w - - 0 1
+//! ```
+//!
+//! Reading is lenient about the fields after the board: the `-` fields and the counters may be
+//! left out, and `H`/`E` are taken for the horse and the elephant too.
+
+use crate::ChessInfo;
+use crate::square::Square;
+use crate::xiangqi::{ChessPiece, ChessType, Hidden};
+
+/// Why a string isn't a FEN.
+#[derive(Clone, PartialEq, Debug)]
+pub enum FenError {
+    /// There is nothing but white space.
+    Empty,
+    /// The board doesn't have 10 ranks.
+    RankCount(usize),
+    /// The rank, counted from red's bottom line, doesn't cover exactly 9 files.
+    RankLength { rank: usize, files: usize },
+    /// The letter isn't any chess.
+    UnknownPiece(char),
+    /// The side to move isn't `w`, `r` or `b`.
+    BadSide(String),
+    /// A move counter isn't a number, or the move number is 0.
+    BadCounter(String),
+    /// More fields follow the move counters.
+    TrailingField(String),
+}
+
+impl std::fmt::Display for FenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FenError::Empty => write!(f, "FEN为空"),
+            FenError::RankCount(count) => write!(f, "FEN应有10行棋盘，却有{count}行"),
+            FenError::RankLength { rank, files } => write!(f, "第{rank}行应有9列，却有{files}列"),
+            FenError::UnknownPiece(c) => write!(f, "无法识别的棋子：{c}"),
+            FenError::BadSide(side) => write!(f, "走棋方应为w、r或b，却是{side}"),
+            FenError::BadCounter(counter) => write!(f, "无效的回合数：{counter}"),
+            FenError::TrailingField(field) => write!(f, "多余的内容：{field}"),
+        }
+    }
+}
+
+impl std::error::Error for FenError {}
+
+fn letter(chess_type: ChessType) -> char {
+    match chess_type {
+        ChessType::CHE => 'r',
+        ChessType::MA => 'n',
+        ChessType::XIANG => 'b',
+        ChessType::SHI => 'a',
+        ChessType::JIANG => 'k',
+        ChessType::PAO => 'c',
+        ChessType::BING => 'p',
+    }
+}
+
+fn piece(c: char) -> Result<ChessPiece, FenError> {
+    let chess_type = match c.to_ascii_lowercase() {
+        'r' => ChessType::CHE,
+        'n' | 'h' => ChessType::MA,
+        'b' | 'e' => ChessType::XIANG,
+        'a' => ChessType::SHI,
+        'k' => ChessType::JIANG,
+        'c' => ChessType::PAO,
+        'p' => ChessType::BING,
+        _ => return Err(FenError::UnknownPiece(c)),
+    };
+    Ok(ChessPiece::Chess { chess_type, black: c.is_ascii_lowercase(), hidden: Hidden::Never })
+}
+
+fn counter(field: Option<&str>, default: usize) -> Result<usize, FenError> {
+    match field {
+        None => Ok(default),
+        Some(field) => field.parse().map_err(|_| FenError::BadCounter(field.to_string())),
+    }
+}
+
+impl ChessInfo {
+    /// Read a position from a FEN.
+    ///
+    /// Only the syntax is checked; see [`crate::validation::validate`] for whether the position
+    /// could come up in a game.
+    pub fn from_fen(fen: &str) -> Result<ChessInfo, FenError> {
+        let mut fields = fen.split_whitespace();
+        let board = fields.next().ok_or(FenError::Empty)?;
+        let ranks: Vec<&str> = board.split('/').collect();
+        if ranks.len() != 10 {
+            return Err(FenError::RankCount(ranks.len()));
+        }
+        let mut chesses = [ChessPiece::None; 90];
+        for (i, line) in ranks.iter().enumerate() {
+            let rank = 9 - i;
+            let mut file = 0;
+            for c in line.chars() {
+                // a run of empty squares is 1 to 9 long, `0` is no chess either.
+                if let Some(empty) = c.to_digit(10).filter(|&n| n > 0) {
+                    file += empty as usize;
+                    continue;
+                }
+                let piece = piece(c)?;
+                if let Some(square) = Square::from_file_rank(file, rank) {
+                    chesses[square.index()] = piece;
+                }
+                file += 1;
+            }
+            if file != 9 {
+                return Err(FenError::RankLength { rank, files: file });
+            }
+        }
+        let black = match fields.next() {
+            None | Some("w") | Some("r") => false,
+            Some("b") => true,
+            Some(side) => return Err(FenError::BadSide(side.to_string())),
+        };
+        let mut rest: Vec<&str> = fields.collect();
+        // the castling and en passant fields of international chess, always `-` in xiangqi.
+        while rest.first() == Some(&"-") {
+            rest.remove(0);
+        }
+        let mut rest = rest.into_iter();
+        let quiet = counter(rest.next(), 0)?;
+        let fullmove = counter(rest.next(), 1)?;
+        if fullmove == 0 {
+            return Err(FenError::BadCounter(fullmove.to_string()));
+        }
+        if let Some(field) = rest.next() {
+            return Err(FenError::TrailingField(field.to_string()));
+        }
+        let mut chess = ChessInfo::from_chesses(chesses, black);
+        chess.quiet = quiet;
+        chess.fullmove = fullmove;
+        Ok(chess)
+    }
+
+    /// Write the position as a FEN.
+    ///
+    /// FEN has no letter for a face-down jieqi chess, it's written as the type it moves as; see
+    /// [`ChessInfo::is_jieqi`] for whether the FEN would lose anything.
+    pub fn fen(&self) -> String {
+        let mut result = String::new();
+        for rank in (0..10).rev() {
+            let mut empty = 0;
+            for file in 0..9 {
+                let square = Square::from_file_rank(file, rank).expect("on the board");
+                match self.get(square) {
+                    ChessPiece::None => empty += 1,
+                    ChessPiece::Chess { chess_type, black, .. } => {
+                        if empty > 0 {
+                            result.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        let c = letter(chess_type);
+                        result.push(if black { c } else { c.to_ascii_uppercase() });
+                    }
+                }
+            }
+            if empty > 0 {
+                result.push_str(&empty.to_string());
+            }
+            if rank > 0 {
+                result.push('/');
+            }
+        }
+        let side = if self.side_to_move() { 'b' } else { 'w' };
+        format!("{result} {side} - - {} {}", self.quiet(), self.fullmove())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPENING: &str = "rnbakabnr/9/1c5c1/p1p1p1p1p/9/9/P1P1P1P1P/1C5C1/9/RNBAKABNR w - - 0 1";
+
+    #[test]
+    fn round_trip_opening() {
+        assert_eq!(ChessInfo::new().fen(), OPENING);
+        let chess = ChessInfo::from_fen(OPENING).unwrap();
+        assert_eq!(chess.fen(), OPENING);
+        assert_eq!(chess.hash(), ChessInfo::new().hash());
+    }
+
+    #[test]
+    fn round_trip_black_to_move() {
+        let fen = "r1bakab1r/9/1cn3nc1/p1p1p1p1p/9/9/P1P1P1P1P/1C2C1N2/9/RNBAKAB1R b - - 3 4";
+        let chess = ChessInfo::from_fen(fen).unwrap();
+        assert!(chess.side_to_move());
+        assert_eq!((chess.quiet(), chess.fullmove()), (3, 4));
+        assert_eq!(chess.fen(), fen);
+        // the short forms read the same.
+        let short = ChessInfo::from_fen("r1bakab1r/9/1cn3nc1/p1p1p1p1p/9/9/P1P1P1P1P/1C2C1H2/9/RNEAKAE1R b 3 4").unwrap();
+        assert_eq!(short.fen(), fen);
+    }
+
+    #[test]
+    fn errors() {
+        let error = |fen: &str| ChessInfo::from_fen(fen).err();
+        assert_eq!(error("  "), Some(FenError::Empty));
+        assert_eq!(error("9/9/9 w"), Some(FenError::RankCount(3)));
+        assert_eq!(error("4k4/9/9/9/9/9/9/9/9/4K3 w"), Some(FenError::RankLength { rank: 0, files: 8 }));
+        assert_eq!(error("4k5/9/9/9/9/9/9/9/9/4K4 w"), Some(FenError::RankLength { rank: 9, files: 10 }));
+        assert_eq!(error("4k4/9/9/9/9/9/9/9/9/4K3X w"), Some(FenError::UnknownPiece('X')));
+        assert_eq!(error("4k4/9/9/9/9/9/9/9/9/04K4 w"), Some(FenError::UnknownPiece('0')));
+        assert_eq!(error("4k4/9/9/9/9/9/9/9/9/4K4 x"), Some(FenError::BadSide("x".to_string())));
+        assert_eq!(error("4k4/9/9/9/9/9/9/9/9/4K4 w - - a 1"), Some(FenError::BadCounter("a".to_string())));
+        assert_eq!(error("4k4/9/9/9/9/9/9/9/9/4K4 w - - 0 0"), Some(FenError::BadCounter("0".to_string())));
+        assert_eq!(error("4k4/9/9/9/9/9/9/9/9/4K4 w - - 0 1 x"), Some(FenError::TrailingField("x".to_string())));
+    }
+}
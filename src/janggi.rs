@@ -0,0 +1,591 @@
+//! Janggi (Korean chess) on the xiangqi board, with the same `ChessType` and `ChessPiece`.
+//!
+//! Han (漢) is the camp `black: false` on rows 0 to 3, Cho (楚) the camp `black: true`; Cho moves
+//! first. There is no river. The general stands in the middle of the palace, and the palace
+//! diagonals are lines for every chess inside it. The elephant steps one position straight and
+//! two diagonally, the cannon always jumps over exactly one chess which isn't a cannon, and
+//! never captures a cannon. Generals facing each other are allowed.
+//!
+//! A camp not in check may pass. Two passes in a row end the game by points.
+
+use crate::chess::{ChessBoard, Outcome};
+use crate::square::{Move, Square};
+use crate::xiangqi::{ChessPiece, ChessType, Hidden, find_jiang};
+
+/// The extra points of Han, for moving second.
+pub const HAN_BONUS: f32 = 1.5;
+
+/// Where the horses and the elephants start, on the columns 1, 2, 6 and 7 seen from the own camp.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Setup {
+    /// 馬象馬象
+    HorseElephantHorseElephant,
+    /// 馬象象馬
+    HorseElephantElephantHorse,
+    /// 象馬馬象
+    ElephantHorseHorseElephant,
+    /// 象馬象馬
+    ElephantHorseElephantHorse,
+}
+
+impl Setup {
+    /// Every setup, in the order offered to the players.
+    pub const ALL: [Setup; 4] = [
+        Setup::HorseElephantHorseElephant,
+        Setup::HorseElephantElephantHorse,
+        Setup::ElephantHorseHorseElephant,
+        Setup::ElephantHorseElephantHorse,
+    ];
+
+    /// The chesses of the setup from the left, like 馬象馬象.
+    pub fn name(self) -> &'static str {
+        match self {
+            Setup::HorseElephantHorseElephant => "馬象馬象",
+            Setup::HorseElephantElephantHorse => "馬象象馬",
+            Setup::ElephantHorseHorseElephant => "象馬馬象",
+            Setup::ElephantHorseElephantHorse => "象馬象馬",
+        }
+    }
+
+    fn order(self) -> [ChessType; 4] {
+        use ChessType::{MA, XIANG};
+        match self {
+            Setup::HorseElephantHorseElephant => [MA, XIANG, MA, XIANG],
+            Setup::HorseElephantElephantHorse => [MA, XIANG, XIANG, MA],
+            Setup::ElephantHorseHorseElephant => [XIANG, MA, MA, XIANG],
+            Setup::ElephantHorseElephantHorse => [XIANG, MA, XIANG, MA],
+        }
+    }
+}
+
+/// A move of janggi: a chess moving, or the camp passing its turn.
+#[derive(Clone, Copy, Debug)]
+pub enum JanggiMove {
+    Step(Move),
+    Pass,
+}
+
+/// Whether the general of the camp `black` is attacked on the board.
+pub fn is_checked(chesses: &[ChessPiece; 90], black: bool) -> bool {
+    let general = match find_jiang(chesses, black) {
+        Some(general) => general,
+        None => return false,
+    };
+    Square::all().any(|from| match chesses[from.index()] {
+        ChessPiece::Chess { chess_type: _, black: camp, .. } if camp != black => {
+            moves::calculate(chesses, from).is_ok_and(|targets| targets.contains(&general))
+        }
+        _ => false,
+    })
+}
+
+/// The points of a chess, counted when the game ends by points.
+pub fn points(chess_type: ChessType) -> f32 {
+    match chess_type {
+        ChessType::CHE => 13.0,
+        ChessType::PAO => 7.0,
+        ChessType::MA => 5.0,
+        ChessType::XIANG => 3.0,
+        ChessType::SHI => 3.0,
+        ChessType::BING => 2.0,
+        ChessType::JIANG => 0.0,
+    }
+}
+
+/// The per-piece generators of janggi, in the shape of [`crate::xiangqi::moves`].
+pub mod moves {
+    use crate::square::Square;
+    use crate::xiangqi::{ChessPiece, ChessType, RulesError};
+
+    const ORTHOGONAL: [(i32, i32); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+
+    /// Whether `square` is in the palace of either camp.
+    fn in_palace(square: Square) -> bool {
+        (3..=5).contains(&square.col()) && (square.row() <= 2 || square.row() >= 7)
+    }
+
+    /// Whether `square` is in the palace of the given camp.
+    fn in_own_palace(square: Square, black: bool) -> bool {
+        in_palace(square) && square.relative_row(black) <= 2
+    }
+
+    /// The palace diagonals leaving `square`: all four from the middle, the inward one from a corner.
+    fn diagonals(square: Square) -> Vec<(i32, i32)> {
+        if !in_palace(square) {
+            return Vec::new();
+        }
+        let (row, col) = (square.row() as i32, square.col() as i32);
+        let middle = if row <= 2 { 1 } else { 8 };
+        if row == middle && col == 4 {
+            vec![(1, 1), (1, -1), (-1, 1), (-1, -1)]
+        } else if row != middle && col != 4 {
+            vec![(middle - row, 4 - col)]
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Every line a sliding chess may follow from `square`, with the positions along it.
+    fn lines(square: Square) -> Vec<Vec<Square>> {
+        let mut result = Vec::new();
+        for (d_row, d_col) in ORTHOGONAL {
+            let mut line = Vec::new();
+            let mut next = square.offset(d_row, d_col);
+            while let Some(s) = next {
+                line.push(s);
+                next = s.offset(d_row, d_col);
+            }
+            result.push(line);
+        }
+        for (d_row, d_col) in diagonals(square) {
+            let mut line = Vec::new();
+            let mut next = square.offset(d_row, d_col);
+            while let Some(s) = next.filter(|&s| in_palace(s)) {
+                line.push(s);
+                next = s.offset(d_row, d_col);
+            }
+            result.push(line);
+        }
+        result
+    }
+
+    fn camp(chesses: &[ChessPiece; 90], square: Square) -> Option<bool> {
+        match chesses[square.index()] {
+            ChessPiece::Chess { chess_type: _, black, .. } => Some(black),
+            ChessPiece::None => None,
+        }
+    }
+
+    fn is_cannon(chesses: &[ChessPiece; 90], square: Square) -> bool {
+        matches!(chesses[square.index()], ChessPiece::Chess { chess_type: ChessType::PAO, .. })
+    }
+
+    /// The general and the advisors: one step along the lines of the own palace.
+    fn palace_steps(chesses: &[ChessPiece; 90], square: Square, black: bool) -> Vec<Square> {
+        ORTHOGONAL.iter().copied().chain(diagonals(square))
+            .filter_map(|(d_row, d_col)| square.offset(d_row, d_col))
+            .filter(|&to| in_own_palace(to, black) && camp(chesses, to) != Some(black))
+            .collect()
+    }
+
+    fn ma(chesses: &[ChessPiece; 90], square: Square, black: bool) -> Vec<Square> {
+        let mut result = Vec::new();
+        for (d_row, d_col) in ORTHOGONAL {
+            let leg = match square.offset(d_row, d_col) {
+                Some(leg) if camp(chesses, leg).is_none() => leg,
+                _ => continue,
+            };
+            for side in [-1, 1] {
+                let (r, c) = if d_row == 0 { (side, d_col) } else { (d_row, side) };
+                if let Some(to) = leg.offset(r, c) {
+                    if camp(chesses, to) != Some(black) {
+                        result.push(to);
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    /// One step straight, then two diagonally outward; both positions passed have to be empty.
+    fn xiang(chesses: &[ChessPiece; 90], square: Square, black: bool) -> Vec<Square> {
+        let mut result = Vec::new();
+        for (d_row, d_col) in ORTHOGONAL {
+            let first = match square.offset(d_row, d_col) {
+                Some(first) if camp(chesses, first).is_none() => first,
+                _ => continue,
+            };
+            for side in [-1, 1] {
+                let (r, c) = if d_row == 0 { (side, d_col) } else { (d_row, side) };
+                let second = match first.offset(r, c) {
+                    Some(second) if camp(chesses, second).is_none() => second,
+                    _ => continue,
+                };
+                if let Some(to) = second.offset(r, c) {
+                    if camp(chesses, to) != Some(black) {
+                        result.push(to);
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    fn che(chesses: &[ChessPiece; 90], square: Square, black: bool) -> Vec<Square> {
+        let mut result = Vec::new();
+        for line in lines(square) {
+            for to in line {
+                match camp(chesses, to) {
+                    None => result.push(to),
+                    Some(camp) => {
+                        if camp != black {
+                            result.push(to);
+                        }
+                        break;
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    /// Over exactly one chess which isn't a cannon, onto an empty position or an enemy which isn't a cannon.
+    fn pao(chesses: &[ChessPiece; 90], square: Square, black: bool) -> Vec<Square> {
+        let mut result = Vec::new();
+        for line in lines(square) {
+            let mut rest = line.into_iter().skip_while(|&s| camp(chesses, s).is_none());
+            match rest.next() {
+                Some(screen) if !is_cannon(chesses, screen) => (),
+                _ => continue,
+            }
+            for to in rest {
+                match camp(chesses, to) {
+                    None => result.push(to),
+                    Some(camp) => {
+                        if camp != black && !is_cannon(chesses, to) {
+                            result.push(to);
+                        }
+                        break;
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    /// Forward or sideways from the start, and diagonally forward along the lines of the enemy palace.
+    fn bing(chesses: &[ChessPiece; 90], square: Square, black: bool) -> Vec<Square> {
+        let forward = if black { -1 } else { 1 };
+        [(forward, 0), (0, 1), (0, -1)].into_iter()
+            .chain(diagonals(square).into_iter().filter(|&(d_row, _)| d_row == forward))
+            .filter_map(|(d_row, d_col)| square.offset(d_row, d_col))
+            .filter(|&to| camp(chesses, to) != Some(black))
+            .collect()
+    }
+
+    /// The pseudo-legal targets of the chess on `square`.
+    pub fn calculate(chesses: &[ChessPiece; 90], square: Square) -> Result<Vec<Square>, RulesError> {
+        let (chess_type, black) = match chesses[square.index()] {
+            ChessPiece::Chess { chess_type, black, .. } => (chess_type, black),
+            ChessPiece::None => return Err(RulesError::EmptySquare(square)),
+        };
+        Ok(match chess_type {
+            ChessType::JIANG | ChessType::SHI => palace_steps(chesses, square, black),
+            ChessType::MA => ma(chesses, square, black),
+            ChessType::XIANG => xiang(chesses, square, black),
+            ChessType::CHE => che(chesses, square, black),
+            ChessType::PAO => pao(chesses, square, black),
+            ChessType::BING => bing(chesses, square, black),
+        })
+    }
+}
+
+/// A game of janggi.
+pub struct Janggi {
+    chesses: [ChessPiece; 90],
+    /// The camp to move; Cho (`black: true`) starts.
+    black: bool,
+    history: Vec<JanggiMove>,
+}
+
+impl Janggi {
+    /// A new game with the horses and elephants of Han and Cho set up as chosen.
+    pub fn new(han: Setup, cho: Setup) -> Self {
+        use ChessType::*;
+        let mut chesses = [ChessPiece::None; 90];
+        for (black, setup) in [(false, han), (true, cho)] {
+            let mut put = |row: usize, col: usize, chess_type: ChessType| {
+                // the camps face each other: the left of Cho is the right of Han.
+                let (row, col) = if black { (9 - row, 8 - col) } else { (row, col) };
+                chesses[row * 9 + col] = ChessPiece::Chess { chess_type, black, hidden: Hidden::Never };
+            };
+            for (col, chess_type) in [0, 3, 5, 8].into_iter().zip([CHE, SHI, SHI, CHE]) {
+                put(0, col, chess_type);
+            }
+            for (col, chess_type) in [1, 2, 6, 7].into_iter().zip(setup.order()) {
+                put(0, col, chess_type);
+            }
+            put(1, 4, JIANG);
+            put(2, 1, PAO);
+            put(2, 7, PAO);
+            for col in [0, 2, 4, 6, 8] {
+                put(3, col, BING);
+            }
+        }
+        Janggi {
+            chesses,
+            black: true,
+            history: Vec::new(),
+        }
+    }
+
+    pub fn chesses(&self) -> &[ChessPiece; 90] {
+        &self.chesses
+    }
+
+    /// Whether the general of the camp `black` is attacked.
+    pub fn is_checked(&self, black: bool) -> bool {
+        is_checked(&self.chesses, black)
+    }
+
+    /// The points of the chesses left to the camp, with [`HAN_BONUS`] for Han.
+    pub fn points(&self, black: bool) -> f32 {
+        let bonus = if black { 0.0 } else { HAN_BONUS };
+        bonus + self.chesses.iter().map(|piece| match piece {
+            ChessPiece::Chess { chess_type, black: camp, .. } if *camp == black => points(*chess_type),
+            _ => 0.0,
+        }).sum::<f32>()
+    }
+
+    /// Whether both camps have passed one after the other.
+    fn passed_twice(&self) -> bool {
+        matches!(self.history.as_slice(), [.., JanggiMove::Pass, JanggiMove::Pass])
+    }
+
+    /// The legal moves of the camp to move, whether the game is over or not.
+    fn moves(&self) -> Vec<JanggiMove> {
+        let black = self.black;
+        let mut result = Vec::new();
+        for from in Square::all() {
+            match self.chesses[from.index()] {
+                ChessPiece::Chess { chess_type: _, black: camp, .. } if camp == black => (),
+                _ => continue,
+            }
+            for to in moves::calculate(&self.chesses, from).unwrap_or_default() {
+                let m = Move::new(&self.chesses, from, to);
+                let mut next = self.chesses;
+                m.apply(&mut next);
+                if !is_checked(&next, black) {
+                    result.push(JanggiMove::Step(m));
+                }
+            }
+        }
+        if !is_checked(&self.chesses, black) {
+            result.push(JanggiMove::Pass);
+        }
+        result
+    }
+
+}
+
+impl ChessBoard for Janggi {
+    type Piece = ChessPiece;
+    type Move = JanggiMove;
+
+    fn size(&self) -> (usize, usize) {
+        (9, 10)
+    }
+
+    fn piece(&self, row: usize, col: usize) -> Option<ChessPiece> {
+        match self.chesses[Square::new(row, col)?.index()] {
+            ChessPiece::None => None,
+            piece => Some(piece),
+        }
+    }
+
+    fn glyph(&self, piece: ChessPiece) -> (&'static str, Option<bool>) {
+        let (chess_type, black) = match piece {
+            ChessPiece::Chess { chess_type, black, .. } => (chess_type, black),
+            ChessPiece::None => return ("", None),
+        };
+        let name = match (chess_type, black) {
+            (ChessType::JIANG, false) => "漢",
+            (ChessType::JIANG, true) => "楚",
+            (ChessType::SHI, _) => "士",
+            (ChessType::XIANG, _) => "象",
+            (ChessType::MA, _) => "馬",
+            (ChessType::CHE, _) => "車",
+            (ChessType::PAO, _) => "包",
+            (ChessType::BING, false) => "兵",
+            (ChessType::BING, true) => "卒",
+        };
+        (name, Some(black))
+    }
+
+    fn legal_moves(&self) -> Vec<JanggiMove> {
+        match self.result() {
+            Some(_) => Vec::new(),
+            None => self.moves(),
+        }
+    }
+
+    /// A pass starts and ends on the general of the camp: click it twice to pass.
+    fn endpoints(&self, m: &JanggiMove) -> ((usize, usize), (usize, usize)) {
+        match m {
+            JanggiMove::Step(m) => ((m.from.row(), m.from.col()), (m.to.row(), m.to.col())),
+            JanggiMove::Pass => {
+                let general = find_jiang(&self.chesses, self.black).map_or((0, 0), |s| (s.row(), s.col()));
+                (general, general)
+            }
+        }
+    }
+
+    fn make_move(&mut self, m: JanggiMove) {
+        if let JanggiMove::Step(step) = m {
+            step.apply(&mut self.chesses);
+        }
+        self.history.push(m);
+        self.black = !self.black;
+    }
+
+    fn undo_move(&mut self) -> Option<JanggiMove> {
+        let m = self.history.pop()?;
+        if let JanggiMove::Step(step) = m {
+            step.revert(&mut self.chesses);
+        }
+        self.black = !self.black;
+        Some(m)
+    }
+
+    fn side_to_move(&self) -> bool {
+        self.black
+    }
+
+    fn result(&self) -> Option<Outcome> {
+        if self.passed_twice() {
+            // the bonus of Han is a half point, there is no tie.
+            let black = self.points(true) > self.points(false);
+            return Some(Outcome::Win { black });
+        }
+        if self.moves().is_empty() {
+            return Some(Outcome::Win { black: !self.black });
+        }
+        None
+    }
+
+    fn camp_name(&self, black: bool) -> &'static str {
+        match black {
+            true => "楚方",
+            false => "汉方",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A board holding the chesses `(row, col, type, camp)`, the camp `black` to move.
+    fn position(chesses: &[(usize, usize, ChessType, bool)], black: bool) -> Janggi {
+        let mut board = [ChessPiece::None; 90];
+        for &(row, col, chess_type, camp) in chesses {
+            board[row * 9 + col] = ChessPiece::Chess { chess_type, black: camp, hidden: Hidden::Never };
+        }
+        Janggi { chesses: board, black, history: Vec::new() }
+    }
+
+    /// The targets of the chess on `(row, col)`, as `(row, col)` sorted.
+    fn targets(game: &Janggi, row: usize, col: usize) -> Vec<(usize, usize)> {
+        let square = Square::new(row, col).unwrap();
+        let mut result: Vec<_> = moves::calculate(game.chesses(), square).unwrap().into_iter()
+            .map(|s| (s.row(), s.col()))
+            .collect();
+        result.sort();
+        result
+    }
+
+    /// The generals out of the way, in the corners of their palaces.
+    const GENERALS: [(usize, usize, ChessType, bool); 2] = [(0, 5, ChessType::JIANG, false), (9, 5, ChessType::JIANG, true)];
+
+    #[test]
+    fn palace_diagonals() {
+        use ChessType::*;
+        let game = position(&[(1, 4, JIANG, false), (9, 3, JIANG, true)], false);
+        assert_eq!(targets(&game, 1, 4), [(0, 3), (0, 4), (0, 5), (1, 3), (1, 5), (2, 3), (2, 4), (2, 5)]);
+        // no diagonal from the middle of a side.
+        let game = position(&[(0, 4, JIANG, false), (9, 3, JIANG, true)], false);
+        assert_eq!(targets(&game, 0, 4), [(0, 3), (0, 5), (1, 4)]);
+
+        let game = position(&[GENERALS[0], GENERALS[1], (0, 3, SHI, false), (9, 3, SHI, true)], false);
+        assert_eq!(targets(&game, 0, 3), [(0, 4), (1, 3), (1, 4)]);
+        assert_eq!(targets(&game, 9, 3), [(8, 3), (8, 4), (9, 4)]);
+
+        // the chariot follows the diagonal up to the far corner, not out of the palace.
+        let game = position(&[GENERALS[0], GENERALS[1], (7, 3, CHE, false)], false);
+        let chariot = targets(&game, 7, 3);
+        assert!(chariot.contains(&(8, 4)) && chariot.contains(&(9, 5)));
+        assert!(!chariot.contains(&(6, 2)));
+
+        // the cannon jumps along the diagonal over the middle.
+        let game = position(&[(0, 4, JIANG, false), GENERALS[1], (2, 3, PAO, false), (1, 4, BING, true)], false);
+        assert_eq!(targets(&game, 2, 3), [(0, 5)]);
+        let game = position(&[(0, 4, JIANG, false), GENERALS[1], (2, 3, PAO, false), (1, 4, BING, true), (0, 5, MA, true)], false);
+        assert!(targets(&game, 2, 3).contains(&(0, 5)));
+    }
+
+    #[test]
+    fn cannon_screens() {
+        use ChessType::*;
+        // a soldier screen: the empty positions beyond, but no enemy cannon.
+        let game = position(&[GENERALS[0], GENERALS[1], (4, 0, PAO, false), (4, 2, BING, false), (4, 5, PAO, true)], false);
+        assert_eq!(targets(&game, 4, 0), [(4, 3), (4, 4)]);
+        // a cannon is no screen.
+        let game = position(&[GENERALS[0], GENERALS[1], (4, 0, PAO, false), (4, 2, PAO, true), (4, 5, MA, true)], false);
+        assert_eq!(targets(&game, 4, 0), []);
+        let game = position(&[GENERALS[0], GENERALS[1], (4, 0, PAO, false), (4, 2, BING, true), (4, 5, MA, true)], false);
+        assert_eq!(targets(&game, 4, 0), [(4, 3), (4, 4), (4, 5)]);
+    }
+
+    #[test]
+    fn elephant_path() {
+        use ChessType::*;
+        let open = position(&[GENERALS[0], GENERALS[1], (4, 4, XIANG, false)], false);
+        assert_eq!(targets(&open, 4, 4), [(1, 2), (1, 6), (2, 1), (2, 7), (6, 1), (6, 7), (7, 2), (7, 6)]);
+        // blocked on the straight step.
+        let game = position(&[GENERALS[0], GENERALS[1], (4, 4, XIANG, false), (5, 4, BING, true)], false);
+        assert!(!targets(&game, 4, 4).contains(&(7, 2)) && !targets(&game, 4, 4).contains(&(7, 6)));
+        // blocked on the diagonal one.
+        let game = position(&[GENERALS[0], GENERALS[1], (4, 4, XIANG, false), (6, 5, BING, true)], false);
+        assert!(!targets(&game, 4, 4).contains(&(7, 6)));
+        assert!(targets(&game, 4, 4).contains(&(7, 2)));
+    }
+
+    #[test]
+    fn no_pass_in_check() {
+        use ChessType::*;
+        let checked = position(&[(0, 3, JIANG, false), (8, 4, JIANG, true), (5, 4, CHE, false)], true);
+        assert!(checked.is_checked(true));
+        assert!(!checked.legal_moves().iter().any(|m| matches!(m, JanggiMove::Pass)));
+        let quiet = position(&[(0, 3, JIANG, false), (8, 4, JIANG, true), (5, 2, CHE, false)], true);
+        assert!(quiet.legal_moves().iter().any(|m| matches!(m, JanggiMove::Pass)));
+    }
+
+    #[test]
+    fn two_passes_count_points() {
+        let mut game = Janggi::new(Setup::HorseElephantHorseElephant, Setup::ElephantHorseElephantHorse);
+        assert_eq!(game.points(false) - game.points(true), HAN_BONUS);
+        game.make_move(JanggiMove::Pass);
+        assert_eq!(game.result(), None);
+        game.make_move(JanggiMove::Pass);
+        // even material: the bonus wins it for Han.
+        assert_eq!(game.result(), Some(Outcome::Win { black: false }));
+        assert!(game.legal_moves().is_empty());
+
+        game.undo_move();
+        game.undo_move();
+        // a Han horse less.
+        game.chesses[1] = ChessPiece::None;
+        game.make_move(JanggiMove::Pass);
+        game.make_move(JanggiMove::Pass);
+        assert_eq!(game.result(), Some(Outcome::Win { black: true }));
+    }
+
+    #[test]
+    fn setups() {
+        for han in Setup::ALL {
+            for cho in Setup::ALL {
+                let game = Janggi::new(han, cho);
+                let kind = |row: usize, col: usize| match game.chesses()[row * 9 + col] {
+                    ChessPiece::Chess { chess_type: ChessType::MA, .. } => '馬',
+                    ChessPiece::Chess { chess_type: ChessType::XIANG, .. } => '象',
+                    _ => ' ',
+                };
+                // each camp reads its setup from its own left.
+                let han_row: String = [1, 2, 6, 7].into_iter().map(|col| kind(0, col)).collect();
+                let cho_row: String = [7, 6, 2, 1].into_iter().map(|col| kind(9, col)).collect();
+                assert_eq!(han_row, han.name());
+                assert_eq!(cho_row, cho.name());
+            }
+        }
+    }
+}
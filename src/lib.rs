@@ -14,57 +14,228 @@ pub mod xiangqi;
 
 pub mod ui; 
 
+pub mod zobrist; 
+
+pub mod adjudication; 
+
+pub mod bitboard; 
+
+pub mod perft; 
+
+pub mod square; 
+
+#[derive(Clone)]
 pub struct ChessInfo {
     info: Box<[ChessPiece; 9 * 10]>, 
+    /// The zobrist hash of `info`, kept up to date by [`ChessInfo::set`]. 
+    hash: u64, 
+    /// The bitboards of `info`, kept up to date by [`ChessInfo::set`]. 
+    board: Board, 
+    /// The seed of the next jieqi (揭棋) shuffle, `None` to set up ordinary xiangqi. 
+    jieqi: Option<u64>, 
+    /// The camp to move. 
+    black: bool, 
+    /// Plies played since the last capture. 
+    quiet: usize, 
+    /// The number of the current move: 1 at the start, counted up after every move of black. 
+    fullmove: usize, 
 }
 
-use xiangqi::{ChessPiece, ChessType, calculate_operators}; 
+use xiangqi::{ChessPiece, ChessType, Hidden, legal_moves, find_jiang, has_legal_move}; 
+use bitboard::Board; 
+use square::{Move, Square}; 
 
 pub mod chess; 
 
+pub mod banqi; 
+
+pub mod janggi; 
+
+pub mod validation; 
+
+pub mod fen; 
+
+pub mod notation; 
+
+pub mod pgn; 
+use chess::{ChessBoard, Outcome}; 
+
 impl ChessInfo {
+    /// A board set up for the opening. 
+    pub fn new() -> Self {
+        let mut s = ChessInfo {
+            info: Box::new([ChessPiece::None; 9 * 10]), 
+            hash: 0, 
+            board: Board::default(), 
+            jieqi: None, 
+            black: false, 
+            quiet: 0, 
+            fullmove: 1, 
+        }; 
+        s.reset(); 
+        s 
+    }
+
+    /// A jieqi board: every chess but the generals face-down, shuffled by `seed` within its camp. 
+    /// Every later [`ChessInfo::reset`] shuffles again. 
+    pub fn jieqi(seed: u64) -> Self {
+        let mut s = ChessInfo {
+            info: Box::new([ChessPiece::None; 9 * 10]), 
+            hash: 0, 
+            board: Board::default(), 
+            jieqi: Some(seed), 
+            black: false, 
+            quiet: 0, 
+            fullmove: 1, 
+        }; 
+        s.reset(); 
+        s 
+    }
+
+    /// Play a move on the board, keeping the hash, the side to move and the counters up to date. 
+    /// The returned [`UndoInfo`] takes it back with [`ChessInfo::unmake_move`]. 
+    pub fn make_move(&mut self, movement: Move) -> UndoInfo {
+        let undo = UndoInfo {
+            movement, 
+            hash: self.hash, 
+            black: self.black, 
+            quiet: self.quiet, 
+            fullmove: self.fullmove, 
+        }; 
+        self.set(movement.to, movement.piece.revealed()); 
+        self.set(movement.from, ChessPiece::None); 
+        self.quiet = if movement.is_capture() { 0 } else { self.quiet + 1 }; 
+        if self.black {
+            self.fullmove += 1; 
+        }
+        self.set_side_to_move(!self.black); 
+        undo 
+    }
+
+    /// Take back the move `undo` was made for; it has to be the last one played. 
+    pub fn unmake_move(&mut self, undo: UndoInfo) {
+        self.set(undo.movement.from, undo.movement.piece); 
+        self.set(undo.movement.to, undo.movement.captured); 
+        self.hash = undo.hash; 
+        self.black = undo.black; 
+        self.quiet = undo.quiet; 
+        self.fullmove = undo.fullmove; 
+    }
+
+    /// A board holding the given chesses, `black` to move. 
+    pub fn from_chesses(chesses: [ChessPiece; 90], black: bool) -> Self {
+        ChessInfo {
+            hash: zobrist::hash(&chesses) ^ zobrist::side(black), 
+            board: Board::new(&chesses), 
+            info: Box::new(chesses), 
+            jieqi: None, 
+            black, 
+            quiet: 0, 
+            fullmove: 1, 
+        }
+    }
+
+    /// The camp to move. 
+    pub fn side_to_move(&self) -> bool {
+        self.black 
+    }
+
+    /// Hand the turn to the camp `black`, updating the hash. 
+    pub fn set_side_to_move(&mut self, black: bool) {
+        self.hash ^= zobrist::side(self.black) ^ zobrist::side(black); 
+        self.black = black; 
+    }
+
+    /// Plies played since the last capture. 
+    pub fn quiet(&self) -> usize {
+        self.quiet 
+    }
+
+    /// The number of the current move, 1 at the start. 
+    pub fn fullmove(&self) -> usize {
+        self.fullmove 
+    }
+
+    pub fn chesses(&self) -> &[ChessPiece; 90] {
+        &self.info 
+    }
+
+    /// Whether the board is one of jieqi (揭棋): shuffled, or with a face-down or revealed chess on it. 
+    /// Neither FEN nor PGN can tell such chesses from ordinary ones. 
+    pub fn is_jieqi(&self) -> bool {
+        self.jieqi.is_some() || self.info.iter().any(|piece| {
+            matches!(piece, ChessPiece::Chess { chess_type: _, black: _, hidden } if *hidden != Hidden::Never)
+        })
+    }
+
+    /// The bitboards of the chesses on the board. 
+    pub fn board(&self) -> &Board {
+        &self.board 
+    }
+
+    /// The zobrist hash of the chesses on the board and the side to move. 
+    pub fn hash(&self) -> u64 {
+        self.hash 
+    }
+
+    pub fn get(&self, square: Square) -> ChessPiece {
+        self.info[square.index()]
+    }
+
+    /// Put `piece` on `square`, updating the hash incrementally. 
+    pub fn set(&mut self, square: Square, piece: ChessPiece) {
+        self.hash ^= zobrist::key(self.get(square), square) ^ zobrist::key(piece, square); 
+        self.board.set(square, piece); 
+        self.info[square.index()] = piece; 
+    }
+
     pub fn reset(&mut self) {
+        self.reset_handicap(&Handicap::default()); 
+    }
+
+    /// Set up the opening without the chesses the handicap takes away. 
+    pub fn reset_handicap(&mut self, handicap: &Handicap) {
         let ref mut b = self.info; 
         b.fill(ChessPiece::None);
         use ChessType::*; 
 
         let base = 0; 
         let black = false; 
-        b[base + 0] = ChessPiece::Chess { chess_type: CHE, black }; 
+        b[base + 0] = ChessPiece::Chess { chess_type: CHE, black, hidden: Hidden::Never }; 
         b[base + 8] = b[base + 0]; 
-        b[base + 1] = ChessPiece::Chess { chess_type: MA, black};
+        b[base + 1] = ChessPiece::Chess { chess_type: MA, black, hidden: Hidden::Never };
         b[base + 7] = b[base + 1]; 
-        b[base + 2] = ChessPiece::Chess { chess_type: XIANG, black}; 
+        b[base + 2] = ChessPiece::Chess { chess_type: XIANG, black, hidden: Hidden::Never }; 
         b[base + 6] = b[base + 2]; 
-        b[base + 3] = ChessPiece::Chess { chess_type: SHI, black};
+        b[base + 3] = ChessPiece::Chess { chess_type: SHI, black, hidden: Hidden::Never };
         b[base + 5] = b[base + 3]; 
-        b[base + 4] = ChessPiece::Chess { chess_type: JIANG, black};
+        b[base + 4] = ChessPiece::Chess { chess_type: JIANG, black, hidden: Hidden::Never };
 
         let base = 81; 
         let black = true; 
-        b[base + 0] = ChessPiece::Chess { chess_type: CHE, black }; 
+        b[base + 0] = ChessPiece::Chess { chess_type: CHE, black, hidden: Hidden::Never }; 
         b[base + 8] = b[base + 0]; 
-        b[base + 1] = ChessPiece::Chess { chess_type: MA, black};
+        b[base + 1] = ChessPiece::Chess { chess_type: MA, black, hidden: Hidden::Never };
         b[base + 7] = b[base + 1]; 
-        b[base + 2] = ChessPiece::Chess { chess_type: XIANG, black}; 
+        b[base + 2] = ChessPiece::Chess { chess_type: XIANG, black, hidden: Hidden::Never }; 
         b[base + 6] = b[base + 2]; 
-        b[base + 3] = ChessPiece::Chess { chess_type: SHI, black};
+        b[base + 3] = ChessPiece::Chess { chess_type: SHI, black, hidden: Hidden::Never };
         b[base + 5] = b[base + 3]; 
-        b[base + 4] = ChessPiece::Chess { chess_type: JIANG, black};
+        b[base + 4] = ChessPiece::Chess { chess_type: JIANG, black, hidden: Hidden::Never };
 
         let base = 18; 
         let black = false; 
-        b[base + 1] = ChessPiece::Chess { chess_type: PAO, black };
+        b[base + 1] = ChessPiece::Chess { chess_type: PAO, black, hidden: Hidden::Never };
         b[base + 7] = b[base + 1];
 
         let base = 63;  
         let black = true; 
-        b[base + 1] = ChessPiece::Chess { chess_type: PAO, black };
+        b[base + 1] = ChessPiece::Chess { chess_type: PAO, black, hidden: Hidden::Never };
         b[base + 7] = b[base + 1];
 
         let base = 27; 
         let black = false; 
-        b[base + 0] = ChessPiece::Chess { chess_type: BING, black };
+        b[base + 0] = ChessPiece::Chess { chess_type: BING, black, hidden: Hidden::Never };
         b[base + 2] = b[base]; 
         b[base + 4] = b[base]; 
         b[base + 6] = b[base]; 
@@ -72,11 +243,80 @@ impl ChessInfo {
 
         let base = 54; 
         let black = true; 
-        b[base + 0] = ChessPiece::Chess { chess_type: BING, black };
+        b[base + 0] = ChessPiece::Chess { chess_type: BING, black, hidden: Hidden::Never };
         b[base + 2] = b[base]; 
         b[base + 4] = b[base]; 
         b[base + 6] = b[base]; 
         b[base + 8] = b[base]; 
+
+        for (chess_type, count) in [(CHE, handicap.chariots), (MA, handicap.horses), (PAO, handicap.cannons)] {
+            let removed: Vec<Square> = Square::all()
+                .filter(|&s| matches!(b[s.index()], ChessPiece::Chess { chess_type: t, black, .. } if t == chess_type && black == handicap.black))
+                .take(count)
+                .collect(); 
+            for square in removed {
+                b[square.index()] = ChessPiece::None; 
+            }
+        }
+
+        if let Some(seed) = self.jieqi {
+            self.jieqi = Some(self.shuffle(seed)); 
+        }
+        // the other camp of a handicap starts with its extra moves. 
+        self.black = handicap.extra_moves > 0 && !handicap.black; 
+        self.quiet = 0; 
+        self.fullmove = 1; 
+        self.hash = zobrist::hash(&self.info) ^ zobrist::side(self.black); 
+        self.board = Board::new(&self.info); 
+    }
+
+    /// Turn every chess but the generals face-down, dealing the real types of each camp 
+    /// over its squares at random. Returns the seed for the next shuffle. 
+    fn shuffle(&mut self, mut seed: u64) -> u64 {
+        for camp in [false, true] {
+            let mut squares = Vec::new(); 
+            let mut types = Vec::new(); 
+            for square in Square::all() {
+                match self.get(square) {
+                    ChessPiece::Chess { chess_type, black, .. } if black == camp && chess_type != ChessType::JIANG => {
+                        squares.push(square); 
+                        types.push(chess_type); 
+                    }
+                    _ => (), 
+                }
+            }
+            utils::shuffle(&mut types, &mut seed); 
+            for (square, real) in squares.into_iter().zip(types) {
+                if let ChessPiece::Chess { chess_type, black, .. } = self.get(square) {
+                    self.info[square.index()] = ChessPiece::Chess { chess_type, black, hidden: Hidden::FaceDown(real) }; 
+                }
+            }
+        }
+        utils::splitmix(&mut seed) 
+    }
+}
+
+impl Default for ChessInfo {
+    fn default() -> Self {
+        Self::new() 
+    }
+}
+
+/// What [`ChessInfo::make_move`] needs to take a move back. 
+#[derive(Clone, Copy, Debug)]
+pub struct UndoInfo {
+    movement: Move, 
+    /// The hash of the board before the move. 
+    hash: u64, 
+    /// The side to move and the counters before the move. 
+    black: bool, 
+    quiet: usize, 
+    fullmove: usize, 
+}
+
+impl UndoInfo {
+    pub fn movement(&self) -> &Move {
+        &self.movement 
     }
 }
 
@@ -84,66 +324,314 @@ pub struct MyApp {
     pub chess : ChessInfo, 
     pub animations: VecDeque<Box<dyn FontAnimation>>, 
     pub game: GameController, 
+    /// The handicap being chosen for the next game. 
+    pub handicap: Handicap, 
+    /// The FEN typed in to load a position. 
+    pub fen: String, 
+    /// The PGN typed in to load a game. 
+    pub pgn: String, 
+    /// The players and the event of the game, written into its PGN. 
+    pub headers: pgn::Headers, 
+    /// Why the last FEN or PGN couldn't be loaded. 
+    pub load_errors: Vec<String>, 
+    /// The notation the record of the game is shown in. 
+    pub notation: notation::Notation, 
+}
+
+/// A handicap (让子) game: the stronger camp starts without some chesses, or lets the other 
+/// camp play some moves first. 
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Handicap {
+    /// The camp giving the handicap. 
+    pub black: bool, 
+    /// How many chariots (车), horses (马) and cannons (炮) are taken away, 0 to 2 each; 
+    /// the ones on the lower columns go first. 
+    pub chariots: usize, 
+    pub horses: usize, 
+    pub cannons: usize, 
+    /// Moves the other camp plays in a row before the usual turns start, red first. 
+    pub extra_moves: usize, 
+}
+
+impl Handicap {
+    pub fn is_none(&self) -> bool {
+        self.chariots == 0 && self.horses == 0 && self.cannons == 0 && self.extra_moves == 0 
+    }
+}
+
+/// Written like 让双车两先, or 让子 alone for a handicap of nothing. 
+impl std::fmt::Display for Handicap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "让")?; 
+        for (count, name) in [(self.chariots, "车"), (self.horses, "马"), (self.cannons, "炮")] {
+            match count {
+                0 => (), 
+                1 => write!(f, "单{name}")?, 
+                _ => write!(f, "双{name}")?, 
+            }
+        }
+        match self.extra_moves {
+            0 => write!(f, "{}", if self.is_none() { "子" } else { "" }), 
+            1 => write!(f, "先"), 
+            n => write!(f, "{n}先"), 
+        }
+    }
 }
 
 pub struct GameController {
     pub state: GameState, 
-    pub operators : Vec<MoveOperator>, 
+    pub operators : Vec<UndoInfo>, 
+    /// The operators taken back, the last one to be played again first. 
+    pub undone : Vec<Move>, 
+    /// The handicap the game was started with. 
+    pub handicap : Handicap, 
     /// 0: black; 1: red 
     pub cursors : [Cursor; 2], 
+    /// The game is drawn once [`ChessInfo::quiet`] reaches this count of plies. 
+    pub quiet_limit : usize, 
 }
 
-pub struct Cursor {
-    pub position: Option<usize>, 
-}
+/// The default natural move limit: 60 moves of each side without a capture. 
+pub const DEFAULT_QUIET_LIMIT: usize = 120; 
 
-pub struct MoveOperator {
-    from_chess: ChessPiece, 
-    to_chess: ChessPiece, 
-    from_index: usize, 
-    to_index: usize, 
+pub struct Cursor {
+    pub position: Option<Square>, 
 }
 
+/// The state of the game as the GUI sees it; the camp to move is [`ChessInfo::side_to_move`]. 
 pub enum GameState {
-    RedTurn(Option<Select>), 
-    BlackTurn(Option<Select>), 
+    /// The game goes on, with the chess the camp to move has selected. 
+    Playing(Option<Select>), 
+    /// The game is over; `black` is the camp of the winner. 
     Win {
         black: bool, 
     }, 
+    /// The game is over without a winner. 
+    Draw, 
 }
 
 pub struct Select {
-    select_id: usize, 
-    move_support: Vec<usize>, 
+    select_id: Square, 
+    move_support: Vec<Square>, 
 }
 
 impl GameController {
+    pub fn new() -> Self {
+        let mut s = GameController {
+            state : GameState::Playing(None), 
+            operators : Vec::new(), 
+            undone : Vec::new(), 
+            quiet_limit : DEFAULT_QUIET_LIMIT, 
+            handicap : Handicap::default(), 
+            cursors : [Cursor { position: None }, Cursor { position: None }], 
+        }; 
+        s.reset(); 
+        s 
+    }
+
+    /// Check whether the camp to move has lost, by checkmate, stalemate or a captured general, 
+    /// or whether a long repetition has to be adjudicated, and finish the game if so. 
+    /// Called after every committed operator. 
+    pub fn settle(&mut self, chess: &ChessInfo) {
+        if let GameState::Playing(_) = self.state {
+            let black = chess.side_to_move(); 
+            if find_jiang(&chess.info, black).is_none() || !has_legal_move(chess.board(), black) {
+                self.state = GameState::Win { black: !black }; 
+            } else if let Some(state) = adjudication::adjudicate(self, chess) {
+                self.state = state; 
+            } else if chess.quiet() >= self.quiet_limit {
+                self.state = GameState::Draw; 
+            }
+        }
+    }
+
+    /// Commit a legal move of the camp to move, then settle the game. 
+    pub fn play(&mut self, chess: &mut ChessInfo, movement: Move) {
+        // a new move forgets the ones taken back. 
+        self.undone.clear(); 
+        self.commit(chess, movement); 
+    }
+
+    fn commit(&mut self, chess: &mut ChessInfo, movement: Move) {
+        self.operators.push(chess.make_move(movement)); 
+        // the other camp of a handicap plays its extra moves in a row, then red starts. 
+        let n = self.operators.len(); 
+        if n < self.handicap.extra_moves {
+            chess.set_side_to_move(!self.handicap.black); 
+        } else if n == self.handicap.extra_moves {
+            chess.set_side_to_move(false); 
+        }
+        self.state = GameState::Playing(None); 
+        self.settle(chess); 
+    }
+
+    /// Take back the last operator, restoring the board, the side to move and the counters. 
+    pub fn undo(&mut self, chess: &mut ChessInfo) -> Option<UndoInfo> {
+        let m = self.operators.pop()?; 
+        chess.unmake_move(m); 
+        self.undone.push(m.movement); 
+        self.state = GameState::Playing(None); 
+        Some(m) 
+    }
+
+    /// Play again the last operator taken back by [`GameController::undo`]. 
+    pub fn redo(&mut self, chess: &mut ChessInfo) -> Option<Move> {
+        let movement = self.undone.pop()?; 
+        self.commit(chess, movement); 
+        Some(movement) 
+    }
+
+    /// The operators played so far, written in the notation. 
+    pub fn record(&self, chess: &ChessInfo, notation: notation::Notation) -> Vec<String> {
+        // walk back from the current board, describing each operator on the board before it. 
+        let mut board = *chess.chesses(); 
+        let mut result: Vec<String> = self.operators.iter().rev().map(|undo| {
+            undo.movement.revert(&mut board); 
+            notation.write(&board, &undo.movement)
+        }).collect(); 
+        result.reverse(); 
+        result 
+    }
+
+    /// The record numbered by full moves as PGN does, a line for each number: `1. 炮二平五 马8进7`, 
+    /// or `1... 马8进7` when black moves first or once more in a row. 
+    pub fn numbered_record(&self, chess: &ChessInfo, notation: notation::Notation) -> Vec<String> {
+        let mut lines: Vec<String> = Vec::new(); 
+        let mut last_black = None; 
+        for (undo, text) in self.operators.iter().zip(self.record(chess, notation)) {
+            match lines.last_mut() {
+                Some(line) if undo.black && last_black == Some(false) => {
+                    line.push(' '); 
+                    line.push_str(&text); 
+                }
+                _ if undo.black => lines.push(format!("{}... {text}", undo.fullmove)), 
+                _ => lines.push(format!("{}. {text}", undo.fullmove)), 
+            }
+            last_black = Some(undo.black); 
+        }
+        lines 
+    }
+
+    /// How many times the current position has occurred, itself included. 
+    /// 
+    /// Positions are compared by the hashes recorded in `operators`, which cover the side to move. 
+    pub fn repetitions(&self, chess: &ChessInfo) -> usize {
+        let current = chess.hash(); 
+        1 + self.operators.iter()
+            .filter(|m| m.hash == current)
+            .count()
+    }
+
     pub fn reset(&mut self) {
-        self.state = GameState::BlackTurn(None);
         self.operators.clear(); 
+        self.undone.clear(); 
+        self.state = GameState::Playing(None); 
         self.cursors = [Cursor {
-            position: Some(4), 
+            position: Square::new(0, 4), 
         }, Cursor {
-            position: Some(85), 
+            position: Square::new(9, 4), 
         }]; 
     }
 }
 
+impl Default for GameController {
+    fn default() -> Self {
+        Self::new() 
+    }
+}
+
+/// The xiangqi rules behind [`ChessBoard`]: a board with the controller keeping its turns, 
+/// history and result. 
+pub struct Xiangqi {
+    pub chess: ChessInfo, 
+    pub game: GameController, 
+}
+
+impl Xiangqi {
+    pub fn new() -> Self {
+        Xiangqi {
+            chess: ChessInfo::new(), 
+            game: GameController::new(), 
+        }
+    }
+}
+
+impl Default for Xiangqi {
+    fn default() -> Self {
+        Self::new() 
+    }
+}
+
+impl ChessBoard for Xiangqi {
+    type Piece = ChessPiece; 
+    type Move = Move; 
+
+    fn size(&self) -> (usize, usize) {
+        (9, 10)
+    }
+
+    fn piece(&self, row: usize, col: usize) -> Option<ChessPiece> {
+        match self.chess.get(Square::new(row, col)?) {
+            ChessPiece::None => None, 
+            piece => Some(piece), 
+        }
+    }
+
+    fn glyph(&self, piece: ChessPiece) -> (&'static str, Option<bool>) {
+        match piece {
+            ChessPiece::Chess { black, .. } => (piece.name(), Some(black)), 
+            ChessPiece::None => ("", None), 
+        }
+    }
+
+    fn legal_moves(&self) -> Vec<Move> {
+        match self.game.state {
+            GameState::Playing(_) => legal_moves(self.chess.chesses(), self.chess.side_to_move()), 
+            _ => Vec::new(), 
+        }
+    }
+
+    fn endpoints(&self, m: &Move) -> ((usize, usize), (usize, usize)) {
+        ((m.from.row(), m.from.col()), (m.to.row(), m.to.col()))
+    }
+
+    fn make_move(&mut self, m: Move) {
+        self.game.play(&mut self.chess, m); 
+    }
+
+    fn undo_move(&mut self) -> Option<Move> {
+        let m = self.game.undo(&mut self.chess)?; 
+        Some(m.movement)
+    }
+
+    fn side_to_move(&self) -> bool {
+        self.chess.side_to_move() 
+    }
+
+    fn result(&self) -> Option<Outcome> {
+        match self.game.state {
+            GameState::Win { black } => Some(Outcome::Win { black }), 
+            GameState::Draw => Some(Outcome::Draw), 
+            _ => None, 
+        }
+    }
+}
+
 pub trait FontAnimation {
-    fn change(&mut self) -> Option<(usize, Color32)>; 
+    fn change(&mut self) -> Option<(Square, Color32)>; 
 }
 
 // every color displays on 400 ms, one update happens with 17 ms. 24 clicks for a tip 
 // three rounds, every thing done, the last one is 24 * 6 = 144 
 pub struct ClickFontAnimation {
-    position : usize, 
+    position : Square, 
     origin : Color32, 
     target : Color32, 
     val : i32, 
 }
 
 impl FontAnimation for ClickFontAnimation {
-    fn change(&mut self) -> Option<(usize, Color32)> {
+    fn change(&mut self) -> Option<(Square, Color32)> {
         self.val += 1; 
         if self.val >= 144 {
             self.val = 144; 
@@ -181,6 +669,8 @@ impl App for MyApp {
             a.swap_remove_back(r);
         }
         let mut click = None;  
+        let mut load_fen = false; 
+        let mut load_pgn = false; 
         egui::CentralPanel::default().show(ctx, |ui| {
             egui::menu::bar(ui, |ui| {
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
@@ -195,52 +685,41 @@ impl App for MyApp {
             }); 
             ui.vertical_centered(|ui | 
             {
+                match self.game.state {
+                    GameState::Win { black } => {
+                        let how = if has_legal_move(self.chess.board(), !black) {
+                            "长打判负"
+                        } else if self.chess.board().is_checked(!black) {
+                            "绝杀"
+                        } else {
+                            "困毙"
+                        }; 
+                        let text = match black {
+                            true => format!("{how}，黑方胜"), 
+                            false => format!("{how}，红方胜"), 
+                        }; 
+                        ui.label(RichText::new(text).size(40.0).strong()); 
+                        ui.separator(); 
+                    }
+                    GameState::Draw => {
+                        let text = if self.chess.quiet() >= self.game.quiet_limit { "自然限着，和棋" } else { "和棋" }; 
+                        ui.label(RichText::new(text).size(40.0).strong()); 
+                        ui.separator(); 
+                    }
+                    _ => (), 
+                }
                 egui::Grid::new("chess board").num_columns(9).show(ui, |ui| {
                     for i in 0..10 {
                         for j in 0..9 {
-                            let val = self.chess.info[i * 9 + j]; 
-                            let word; 
-                            match val {
-                                ChessPiece::None => {
-                                    word = "";  
-                                }
-                                ChessPiece::Chess { chess_type, black } => {
-                                    word = match chess_type {
-                                        ChessType::BING => match black {
-                                            true => "卒", 
-                                            false => "兵", 
-                                        }
-                                        ChessType::PAO => match black {
-                                            true => "砲", 
-                                            false => "炮", 
-                                        }
-                                        ChessType::CHE => match black {
-                                            true => "車",
-                                            false => "车",  
-                                        }
-                                        ChessType::MA => match black {
-                                            true => "馬", 
-                                            false => "马", 
-                                        }
-                                        ChessType::XIANG => match black {
-                                            true => "象", 
-                                            false => "相", 
-                                        }
-                                        ChessType::SHI => match black {
-                                            true => "仕", 
-                                            false => "士", 
-                                        }
-                                        ChessType::JIANG => match black {
-                                            true => "将", 
-                                            false => "帅", 
-                                        }
-                                    }; 
-                                }
-                            }
-                            let index = i * 9 + j; 
+                            let index = match Square::new(i, j) {
+                                Some(index) => index, 
+                                None => continue, 
+                            }; 
+                            let val = self.chess.get(index); 
+                            let word = val.name(); 
                             let size = 50.0; 
                             let mut word = RichText::new(word).size(size * 0.7); 
-                            if let ChessPiece::Chess { chess_type : _, black } = val {
+                            if let ChessPiece::Chess { chess_type : _, black, .. } = val {
                                 word = if black {
                                     word.color(Color32::BLACK)
                                 } else {
@@ -268,14 +747,7 @@ impl App for MyApp {
                             }
                             // widget = widget.stroke(Stroke::new(1.0, Color32::GREEN)); 
                             match &self.game.state {
-                                &GameState::RedTurn(Some(ref v)) => {
-                                    if v.select_id == index {
-                                        widget = widget.stroke(Stroke::new(1.3, Color32::RED)); 
-                                    } else if v.move_support.contains(&index) {
-                                        widget = widget.stroke(Stroke::new(1.3, Color32::GREEN)); 
-                                    }
-                                }, 
-                                &GameState::BlackTurn(Some(ref v)) => {
+                                &GameState::Playing(Some(ref v)) => {
                                     if v.select_id == index {
                                         widget = widget.stroke(Stroke::new(1.3, Color32::RED)); 
                                     } else if v.move_support.contains(&index) {
@@ -302,32 +774,119 @@ impl App for MyApp {
 
                 ui.separator(); 
 
-                if ui.button("重置棋局").clicked() {
-                    self.chess.reset(); 
-                    self.game.reset(); 
-                    ctx.request_repaint(); 
+                if !self.game.handicap.is_none() {
+                    ui.label(format!("{}{}", xiangqi::camp_name(self.game.handicap.black), self.game.handicap)); 
                 }
 
+                ui.horizontal(|ui| {
+                    if ui.button("重置棋局").clicked() {
+                        self.chess.reset_handicap(&self.game.handicap); 
+                        self.game.reset(); 
+                        ctx.request_repaint(); 
+                    }
+                    if ui.button("复制FEN").clicked() {
+                        // a FEN would pass the face-down chesses off as ordinary ones. 
+                        if self.chess.is_jieqi() {
+                            self.load_errors = vec!["揭棋的局面无法写成FEN".to_string()]; 
+                        } else {
+                            self.load_errors.clear(); 
+                            ui.output().copied_text = self.chess.fen(); 
+                        }
+                    }
+                    if ui.button("载入FEN").clicked() {
+                        load_fen = true; 
+                    }
+                    ui.text_edit_singleline(&mut self.fen); 
+                }); 
+                for error in self.load_errors.iter() {
+                    ui.label(RichText::new(error).color(Color32::RED)); 
+                }
+
+                ui.collapsing("让子", |ui| {
+                    let ref mut h = self.handicap; 
+                    ui.horizontal(|ui| {
+                        ui.radio_value(&mut h.black, false, "红方让"); 
+                        ui.radio_value(&mut h.black, true, "黑方让"); 
+                    }); 
+                    ui.add(egui::Slider::new(&mut h.chariots, 0..=2).text("车")); 
+                    ui.add(egui::Slider::new(&mut h.horses, 0..=2).text("马")); 
+                    ui.add(egui::Slider::new(&mut h.cannons, 0..=2).text("炮")); 
+                    ui.add(egui::Slider::new(&mut h.extra_moves, 0..=3).text("先")); 
+                    if ui.button("让子开局").clicked() {
+                        self.game.handicap = *h; 
+                        self.chess.reset_handicap(h); 
+                        self.game.reset(); 
+                        ctx.request_repaint(); 
+                    }
+                }); 
+
+                ui.collapsing("自然限着", |ui| {
+                    ui.horizontal(|ui| {
+                        let short = ui.radio_value(&mut self.game.quiet_limit, 60, "60步"); 
+                        let long = ui.radio_value(&mut self.game.quiet_limit, DEFAULT_QUIET_LIMIT, "120步"); 
+                        // a shorter limit may already be reached. 
+                        if short.changed() || long.changed() {
+                            self.game.settle(&self.chess); 
+                        }
+                    }); 
+                    ui.label(format!("连续{}步未吃子判和", self.game.quiet_limit)); 
+                }); 
+
+                ui.collapsing("着法记录", |ui| {
+                    ui.horizontal(|ui| {
+                        ui.radio_value(&mut self.notation, notation::Notation::Chinese, "中文"); 
+                        ui.radio_value(&mut self.notation, notation::Notation::Wxf, "WXF"); 
+                        ui.radio_value(&mut self.notation, notation::Notation::Iccs, "ICCS"); 
+                    }); 
+                    ui.horizontal(|ui| {
+                        // step through the game, even one which is over. 
+                        if ui.button("后退").clicked() {
+                            self.game.undo(&mut self.chess); 
+                        }
+                        if ui.button("前进").clicked() {
+                            self.game.redo(&mut self.chess); 
+                        }
+                        if ui.button("复制PGN").clicked() {
+                            match pgn::write(&self.headers, &self.chess, &self.game, self.notation) {
+                                Ok(text) => {
+                                    self.load_errors.clear(); 
+                                    ui.output().copied_text = text; 
+                                }
+                                Err(e) => self.load_errors = vec![e.to_string()], 
+                            }
+                        }
+                        if ui.button("载入PGN").clicked() {
+                            load_pgn = true; 
+                        }
+                    }); 
+                    ui.text_edit_multiline(&mut self.pgn); 
+                    ui.horizontal_wrapped(|ui| {
+                        for line in self.game.numbered_record(&self.chess, self.notation) {
+                            ui.label(line); 
+                        }
+                    }); 
+                }); 
+
             } ); 
         }); 
 
+        if load_fen {
+            self.load_fen(); 
+            ctx.request_repaint(); 
+        }
+        if load_pgn {
+            self.load_pgn(); 
+            ctx.request_repaint(); 
+        }
+
         {
             let input = ctx.input();
-            if input.key_released(egui::Key::PageUp) {
+            // a finished game accepts nothing until it's reset. 
+            let finished = matches!(self.game.state, GameState::Win { .. } | GameState::Draw); 
+            if input.key_released(egui::Key::PageUp) && !finished {
                 // roll the game operator ~ 
-                let p = self.game.operators.pop();  
-                if let Some(m) = p {
-                    self.chess.info[m.from_index] = m.from_chess; 
-                    self.chess.info[m.to_index] = m.to_chess; 
-                    match self.game.state {
-                        GameState::RedTurn(_) => {
-                            self.game.state = GameState::BlackTurn(None);
-                        }
-                        GameState::BlackTurn(_) => {
-                            self.game.state = GameState::RedTurn(None);
-                        }
-                        _ => (), 
-                    }
+                let p = self.game.undo(&mut self.chess);  
+                if p.is_some() {
                     return ; 
                 }
             }
@@ -336,7 +895,7 @@ impl App for MyApp {
                 let index = self.game.cursors[1].position; 
                 match index {
                     Some(index) => {
-                        if let GameState::RedTurn(ref mut v) = self.game.state {
+                        if let (GameState::Playing(ref mut v), true) = (&mut self.game.state, self.chess.side_to_move()) {
                             match v {
                                 Some(Select {
                                     select_id,
@@ -347,34 +906,26 @@ impl App for MyApp {
                                     } else if move_support.contains(&index) {
 
                                         // great, commit this operator ~ 
-                                        self.game.operators.push(MoveOperator {
-                                            from_chess: self.chess.info[*select_id], 
-                                            to_chess: self.chess.info[index], 
-                                            from_index: *select_id, 
-                                            to_index: index, 
-                                        }); 
-                                        self.chess.info[index] = self.chess.info[*select_id]; 
-                                        self.chess.info[*select_id] = ChessPiece::None; 
-
-                                        self.game.state = GameState::BlackTurn(None); 
+                                        let movement = Move::new(&self.chess.info, *select_id, index); 
+                                        self.game.play(&mut self.chess, movement); 
                                         
                                     } else {
-                                        if let ChessPiece::Chess { chess_type: _, black } = self.chess.info[index] { 
+                                        if let ChessPiece::Chess { chess_type: _, black, .. } = self.chess.get(index) { 
                                             if black {
                                                 *v = Some(Select {
                                                     select_id: index, 
-                                                    move_support: calculate_operators(&self.chess.info, index), 
+                                                    move_support: self.chess.board().legal_targets(index).iter().collect(), 
                                                 }); 
                                             }
                                         }
                                     }
                                 },
                                 None => {
-                                    if let ChessPiece::Chess { chess_type: _, black } = self.chess.info[index] { 
+                                    if let ChessPiece::Chess { chess_type: _, black, .. } = self.chess.get(index) { 
                                         if black {
                                             *v = Some(Select {
                                                 select_id: index, 
-                                                move_support: calculate_operators(&self.chess.info, index), 
+                                                move_support: self.chess.board().legal_targets(index).iter().collect(), 
                                             }); 
                                         }
                                     }
@@ -390,12 +941,9 @@ impl App for MyApp {
                 let ref mut c = self.game.cursors[1]; 
                 match &mut c.position {
                     Some(val) => {
-                        let mut row = *val / 9; 
-                        let col = *val % 9; 
-                        if row > 0 {
-                            row -= 1;  
+                        if let Some(next) = val.offset(-1, 0) {
+                            *val = next; 
                         }
-                        *val = row * 9 + col; 
                     }
                     None => (), 
                 }
@@ -405,12 +953,9 @@ impl App for MyApp {
                 let ref mut c = self.game.cursors[1]; 
                 match &mut c.position {
                     Some(val) => {
-                        let mut row = *val / 9; 
-                        let col = *val % 9; 
-                        if row < 9 { 
-                            row += 1;  
+                        if let Some(next) = val.offset(1, 0) {
+                            *val = next; 
                         }
-                        *val = row * 9 + col; 
                     }
                     None => (), 
                 }
@@ -420,12 +965,9 @@ impl App for MyApp {
                 let ref mut c = self.game.cursors[1]; 
                 match &mut c.position {
                     Some(val) => {
-                        let row = *val / 9; 
-                        let mut col = *val % 9; 
-                        if col > 0 {
-                            col -= 1; 
+                        if let Some(next) = val.offset(0, -1) {
+                            *val = next; 
                         }
-                        *val = row * 9 + col; 
                     }
                     None => (), 
                 }
@@ -435,12 +977,9 @@ impl App for MyApp {
                 let ref mut c = self.game.cursors[1]; 
                 match &mut c.position {
                     Some(val) => {
-                        let row = *val / 9; 
-                        let mut col = *val % 9; 
-                        if col < 8 { 
-                            col += 1; 
+                        if let Some(next) = val.offset(0, 1) {
+                            *val = next; 
                         }
-                        *val = row * 9 + col; 
                     }
                     None => (), 
                 }
@@ -450,7 +989,7 @@ impl App for MyApp {
                 let index = self.game.cursors[0].position; 
                 match index {
                     Some(index) => {
-                        if let GameState::BlackTurn(ref mut v) = self.game.state {
+                        if let (GameState::Playing(ref mut v), false) = (&mut self.game.state, self.chess.side_to_move()) {
                             match v {
                                 Some(Select {
                                     select_id,
@@ -461,34 +1000,26 @@ impl App for MyApp {
                                     } else if move_support.contains(&index) {
 
                                         // great, commit this operator ~ 
-                                        self.game.operators.push(MoveOperator {
-                                            from_chess: self.chess.info[*select_id], 
-                                            to_chess: self.chess.info[index], 
-                                            from_index: *select_id, 
-                                            to_index: index, 
-                                        }); 
-                                        self.chess.info[index] = self.chess.info[*select_id]; 
-                                        self.chess.info[*select_id] = ChessPiece::None; 
-
-                                        self.game.state = GameState::RedTurn(None); 
+                                        let movement = Move::new(&self.chess.info, *select_id, index); 
+                                        self.game.play(&mut self.chess, movement); 
                                         
                                     } else {
-                                        if let ChessPiece::Chess { chess_type: _, black } = self.chess.info[index] { 
+                                        if let ChessPiece::Chess { chess_type: _, black, .. } = self.chess.get(index) { 
                                             if !black {
                                                 *v = Some(Select {
                                                     select_id: index, 
-                                                    move_support: calculate_operators(&self.chess.info, index), 
+                                                    move_support: self.chess.board().legal_targets(index).iter().collect(), 
                                                 }); 
                                             }
                                         }
                                     }
                                 },
                                 None => {
-                                    if let ChessPiece::Chess { chess_type: _, black } = self.chess.info[index] { 
+                                    if let ChessPiece::Chess { chess_type: _, black, .. } = self.chess.get(index) { 
                                         if !black {
                                             *v = Some(Select {
                                                 select_id: index, 
-                                                move_support: calculate_operators(&self.chess.info, index), 
+                                                move_support: self.chess.board().legal_targets(index).iter().collect(), 
                                             }); 
                                         }
                                     }
@@ -504,12 +1035,9 @@ impl App for MyApp {
                 let ref mut c = self.game.cursors[0]; 
                 match &mut c.position {
                     Some(val) => {
-                        let mut row = *val / 9; 
-                        let col = *val % 9; 
-                        if row > 0 {
-                            row -= 1;  
+                        if let Some(next) = val.offset(-1, 0) {
+                            *val = next; 
                         }
-                        *val = row * 9 + col; 
                     }
                     None => (), 
                 }
@@ -519,12 +1047,9 @@ impl App for MyApp {
                 let ref mut c = self.game.cursors[0]; 
                 match &mut c.position {
                     Some(val) => {
-                        let mut row = *val / 9; 
-                        let col = *val % 9; 
-                        if row < 9 { 
-                            row += 1;  
+                        if let Some(next) = val.offset(1, 0) {
+                            *val = next; 
                         }
-                        *val = row * 9 + col; 
                     }
                     None => (), 
                 }
@@ -534,12 +1059,9 @@ impl App for MyApp {
                 let ref mut c = self.game.cursors[0]; 
                 match &mut c.position {
                     Some(val) => {
-                        let row = *val / 9; 
-                        let mut col = *val % 9; 
-                        if col > 0 {
-                            col -= 1; 
+                        if let Some(next) = val.offset(0, -1) {
+                            *val = next; 
                         }
-                        *val = row * 9 + col; 
                     }
                     None => (), 
                 }
@@ -549,12 +1071,9 @@ impl App for MyApp {
                 let ref mut c = self.game.cursors[0]; 
                 match &mut c.position {
                     Some(val) => {
-                        let row = *val / 9; 
-                        let mut col = *val % 9; 
-                        if col < 8 { 
-                            col += 1; 
+                        if let Some(next) = val.offset(0, 1) {
+                            *val = next; 
                         }
-                        *val = row * 9 + col; 
                     }
                     None => (), 
                 }
@@ -565,24 +1084,128 @@ impl App for MyApp {
 
 impl MyApp {
     pub fn new() -> Self {
-        let mut s = Self {
-            chess : ChessInfo {
-                info : Box::new([ChessPiece::None; 9 * 10]), 
-            }, 
+        Self::with_chess(ChessInfo::new()) 
+    }
+
+    /// A hot seat game of jieqi (揭棋), shuffled by `seed`. 
+    pub fn jieqi(seed: u64) -> Self {
+        Self::with_chess(ChessInfo::jieqi(seed)) 
+    }
+
+    /// A hot seat game from a set up position, with its side to move. 
+    pub fn from_position(chess: ChessInfo) -> Self {
+        let mut s = Self::with_chess(chess); 
+        s.game.settle(&s.chess); 
+        s 
+    }
+
+    fn with_chess(chess: ChessInfo) -> Self {
+        Self {
+            chess, 
             animations: VecDeque::default(), 
-            game: GameController {
-                state : GameState::BlackTurn(None), 
-                operators : Vec::new(), 
-                cursors: [
-                Cursor {
-                    position: Some(4), 
-                }, 
-                Cursor {
-                    position: Some(85),  
-                }], 
+            game: GameController::new(), 
+            handicap: Handicap::default(), 
+            fen: String::new(), 
+            pgn: String::new(), 
+            headers: pgn::Headers::default(), 
+            load_errors: Vec::new(), 
+            notation: notation::Notation::default(), 
+        }
+    }
+
+    /// Replace the game by the position of `self.fen`, if it's a sound one. 
+    fn load_fen(&mut self) {
+        let chess = match ChessInfo::from_fen(&self.fen) {
+            Ok(chess) => chess, 
+            Err(e) => {
+                self.load_errors = vec![e.to_string()]; 
+                return ; 
             }
         }; 
-        s.chess.reset(); 
-        s 
+        self.load_errors = validation::validate(&chess, chess.side_to_move()).iter()
+            .map(|problem| problem.to_string())
+            .collect(); 
+        if self.load_errors.is_empty() {
+            self.chess = chess; 
+            self.headers = pgn::Headers::default(); 
+            self.game.handicap = Handicap::default(); 
+            self.game.reset(); 
+            self.game.settle(&self.chess); 
+        }
+    }
+
+    /// Replace the game by the one of `self.pgn`, played up to its last move. 
+    fn load_pgn(&mut self) {
+        match pgn::read(&self.pgn) {
+            Ok(game) => {
+                self.load_errors.clear(); 
+                self.headers = game.headers; 
+                self.chess = game.chess; 
+                // the cursors stay where they are. 
+                self.game.state = game.game.state; 
+                self.game.operators = game.game.operators; 
+                self.game.undone.clear(); 
+                self.game.handicap = game.game.handicap; 
+            }
+            Err(e) => self.load_errors = vec![e.to_string()], 
+        }
+    }
+}
+
+impl Default for MyApp {
+    fn default() -> Self {
+        Self::new() 
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*; 
+    use notation::parse_iccs; 
+
+    #[test]
+    fn quiet_limit_draws() {
+        let mut chess = ChessInfo::from_fen("4k4/9/9/9/9/9/9/9/9/3K5 w - - 58 30").unwrap(); 
+        let mut game = GameController::new(); 
+        game.quiet_limit = 60; 
+        let m = parse_iccs(&chess, "d0d1").unwrap(); 
+        game.play(&mut chess, m); 
+        assert!(matches!(game.state, GameState::Playing(_))); 
+        let m = parse_iccs(&chess, "e9e8").unwrap(); 
+        game.play(&mut chess, m); 
+        assert_eq!(chess.quiet(), 60); 
+        assert!(matches!(game.state, GameState::Draw)); 
+
+        game.undo(&mut chess); 
+        assert_eq!(chess.quiet(), 59); 
+        assert!(matches!(game.state, GameState::Playing(_))); 
+        game.redo(&mut chess); 
+        assert!(matches!(game.state, GameState::Draw)); 
+
+        // the default limit goes on to 120 plies. 
+        game.undo(&mut chess); 
+        game.quiet_limit = DEFAULT_QUIET_LIMIT; 
+        game.redo(&mut chess); 
+        assert!(matches!(game.state, GameState::Playing(_))); 
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn record_numbered_by_full_moves() {
+        let mut chess = ChessInfo::new(); 
+        let mut game = GameController::new(); 
+        for text in ["h2e2", "h9g7", "h0g2"] {
+            let m = parse_iccs(&chess, text).unwrap(); 
+            game.play(&mut chess, m); 
+        }
+        assert_eq!(game.numbered_record(&chess, notation::Notation::Chinese), ["1. 炮二平五 马8进7", "2. 马二进三"]); 
+
+        // black starting from a FEN. 
+        let mut chess = ChessInfo::from_fen("rnbakabnr/9/1c5c1/p1p1p1p1p/9/9/P1P1P1P1P/1C5C1/9/RNBAKABNR b - - 0 5").unwrap(); 
+        let mut game = GameController::new(); 
+        for text in ["h9g7", "h2e2"] {
+            let m = parse_iccs(&chess, text).unwrap(); 
+            game.play(&mut chess, m); 
+        }
+        assert_eq!(game.numbered_record(&chess, notation::Notation::Iccs), ["5... h9g7", "6. h2e2"]); 
+    }
+}
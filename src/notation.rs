@@ -0,0 +1,559 @@
+//! Writing moves down and reading them back.
+//!
+//! ICCS coordinates name the square a chess leaves and the one it reaches, like `h2e2` for the
+//! red cannon going to the middle file; they are what engines and online databases speak.
+//!
+//! The Chinese notation is what players read, like 炮二平五 or 马8进7. Each camp counts the
+//! files from its own right, red with Chinese numerals and black with digits, then tells whether
+//! the chess goes forward (进), backward (退) or sideways (平). Chesses moving straight give
+//! how many ranks they go forward or backward, the others the file they reach. Chesses of one
+//! type sharing a file are told apart by 前, 中 and 后, counted from the camp's front, except
+//! advisors and elephants, which the direction already tells apart.
+//!
+//! WXF notation is the same description in Latin letters, like `C2.5` or `H8+7`: the chess is
+//! one of `K A E H R C P`, files are digits for both camps and the direction is `+`, `-` or `.`.
+//! Two chesses sharing a file put `+` (front) or `-` (rear) in place of the file, like `R+-2`,
+//! but advisors and elephants keep it, like `A4+5`;
+//! more of them, or soldiers crowding several files, write their place counted from the front
+//! before the letter and keep the file, like `2P7.6`.
+
+use crate::ChessInfo;
+use crate::square::{Move, Square};
+use crate::xiangqi::{ChessPiece, ChessType, RulesError, calculate_operators, legal_operators};
+
+/// Why a written move can't be played.
+#[derive(Clone, PartialEq, Debug)]
+pub enum NotationError {
+    /// The text isn't a move in the notation at all.
+    Syntax(String),
+    /// There isn't any chess on the square the move starts from.
+    EmptySquare(Square),
+    /// The chess on the square belongs to the camp which doesn't move.
+    NotToMove(Square),
+    /// The chess can't ever go from `from` to `to` on this board.
+    Unreachable { from: Square, to: Square },
+    /// The move would leave the own general in check, or facing the other one.
+    SelfCheck { from: Square, to: Square },
+    /// No move of the camp to move fits the description.
+    NoSuchMove(String),
+    /// The board itself breaks the rules.
+    Rules(RulesError),
+}
+
+impl std::fmt::Display for NotationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NotationError::Syntax(text) => write!(f, "无法识别的着法：{text}"),
+            NotationError::EmptySquare(square) => write!(f, "{square}没有棋子"),
+            NotationError::NotToMove(square) => write!(f, "{square}的棋子不是走棋方的"),
+            NotationError::Unreachable { from, to } => write!(f, "{from}的棋子走不到{to}"),
+            NotationError::SelfCheck { from, to } => write!(f, "{from}走到{to}后己方被将军"),
+            NotationError::NoSuchMove(text) => write!(f, "走棋方没有着法{text}"),
+            NotationError::Rules(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for NotationError {}
+
+impl From<RulesError> for NotationError {
+    fn from(e: RulesError) -> Self {
+        NotationError::Rules(e)
+    }
+}
+
+/// The move of the chess on `from` to `to`, if it's legal for the camp to move.
+pub fn legal_move(chess: &ChessInfo, from: Square, to: Square) -> Result<Move, NotationError> {
+    match chess.get(from) {
+        ChessPiece::None => return Err(NotationError::EmptySquare(from)),
+        ChessPiece::Chess { chess_type: _, black, .. } if black != chess.side_to_move() => {
+            return Err(NotationError::NotToMove(from));
+        }
+        ChessPiece::Chess { .. } => (),
+    }
+    if !calculate_operators(chess.chesses(), from)?.contains(&to) {
+        return Err(NotationError::Unreachable { from, to });
+    }
+    if !legal_operators(chess.chesses(), from)?.contains(&to) {
+        return Err(NotationError::SelfCheck { from, to });
+    }
+    Ok(Move::new(chess.chesses(), from, to))
+}
+
+/// The move in ICCS coordinates, like `h2e2`.
+pub fn iccs(m: &Move) -> String {
+    format!("{}{}", m.from, m.to)
+}
+
+/// The two squares of a move in ICCS coordinates, whether the move is legal or not.
+///
+/// Upper case letters and one dash between the squares, like `H2-E2`, are accepted too.
+pub fn iccs_squares(text: &str) -> Result<(Square, Square), NotationError> {
+    let syntax = || NotationError::Syntax(text.to_string());
+    let squares = text.trim();
+    if !squares.is_ascii() {
+        return Err(syntax());
+    }
+    let (from, to) = match squares.len() {
+        4 => (&squares[..2], &squares[2..]),
+        5 if &squares[2..3] == "-" => (&squares[..2], &squares[3..]),
+        _ => return Err(syntax()),
+    };
+    let from = from.parse().map_err(|_| syntax())?;
+    let to = to.parse().map_err(|_| syntax())?;
+    Ok((from, to))
+}
+
+/// Read a move in ICCS coordinates, which has to be legal on the board.
+pub fn parse_iccs(chess: &ChessInfo, text: &str) -> Result<Move, NotationError> {
+    let (from, to) = iccs_squares(text)?;
+    legal_move(chess, from, to)
+}
+
+const CHINESE_NUMERALS: [char; 9] = ['一', '二', '三', '四', '五', '六', '七', '八', '九'];
+
+/// The name of the type in the notation, which sticks to the simplified characters.
+fn notation_name(chess_type: ChessType, black: bool) -> char {
+    match (chess_type, black) {
+        (ChessType::JIANG, false) => '帅',
+        (ChessType::JIANG, true) => '将',
+        (ChessType::SHI, false) => '仕',
+        (ChessType::SHI, true) => '士',
+        (ChessType::XIANG, false) => '相',
+        (ChessType::XIANG, true) => '象',
+        (ChessType::MA, _) => '马',
+        (ChessType::CHE, _) => '车',
+        (ChessType::PAO, _) => '炮',
+        (ChessType::BING, false) => '兵',
+        (ChessType::BING, true) => '卒',
+    }
+}
+
+/// The number `n` from 1 to 9 as the camp writes it.
+fn numeral(n: usize, black: bool) -> char {
+    match black {
+        false => CHINESE_NUMERALS[n - 1],
+        true => (b'0' + n as u8) as char,
+    }
+}
+
+/// The file of the square counted from the camp's own right, 1 to 9.
+fn own_file(square: Square, black: bool) -> usize {
+    match black {
+        false => 9 - square.file(),
+        true => square.file() + 1,
+    }
+}
+
+/// The chesses of the type and camp on each file, front to back for the camp.
+fn columns(chesses: &[ChessPiece; 90], chess_type: ChessType, black: bool) -> Vec<Vec<Square>> {
+    let mut result = vec![Vec::new(); 9];
+    for square in Square::all() {
+        if let ChessPiece::Chess { chess_type: t, black: b, .. } = chesses[square.index()] {
+            if t == chess_type && b == black {
+                result[square.file()].push(square);
+            }
+        }
+    }
+    for column in result.iter_mut() {
+        column.sort_by_key(|square| std::cmp::Reverse(square.relative_row(black)));
+    }
+    result
+}
+
+/// Which way a move goes, seen from the camp which plays it.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Direction {
+    Forward,
+    Backward,
+    Sideways,
+}
+
+/// How the chess which moves is told apart from the others of its camp.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Origin {
+    /// The only chess of its type on the file, counted from the camp's right.
+    File(usize),
+    /// The `index`th of `count` chesses of its type on one file, from the camp's front;
+    /// `file` is kept when soldiers crowd more than one file.
+    Tandem { index: usize, count: usize, file: Option<usize> },
+}
+
+/// A move as both the Chinese and the WXF notation describe it.
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct Description {
+    chess_type: ChessType,
+    black: bool,
+    origin: Origin,
+    direction: Direction,
+    /// The ranks gone straight forward or backward, or else the file reached.
+    number: usize,
+}
+
+/// Describe the move, `chesses` being the board before it.
+fn describe(chesses: &[ChessPiece; 90], m: &Move) -> Option<Description> {
+    let (chess_type, black) = match m.piece {
+        ChessPiece::Chess { chess_type, black, .. } => (chess_type, black),
+        ChessPiece::None => return None,
+    };
+    let columns = columns(chesses, chess_type, black);
+    let column = &columns[m.from.file()];
+    // advisors and elephants sharing a file are told apart by the direction, so they keep the
+    // file: 仕四退五, 相三进五.
+    let by_file = matches!(chess_type, ChessType::SHI | ChessType::XIANG);
+    let origin = if column.len() < 2 || by_file {
+        Origin::File(own_file(m.from, black))
+    } else {
+        let index = column.iter().position(|&square| square == m.from).unwrap_or_default();
+        let crowded = columns.iter().filter(|column| column.len() >= 2).count() > 1;
+        Origin::Tandem { index, count: column.len(), file: crowded.then(|| own_file(m.from, black)) }
+    };
+    let from_row = m.from.relative_row(black) as i32;
+    let to_row = m.to.relative_row(black) as i32;
+    let direction = match to_row - from_row {
+        0 => Direction::Sideways,
+        d if d > 0 => Direction::Forward,
+        _ => Direction::Backward,
+    };
+    let number = if m.from.file() == m.to.file() {
+        (to_row - from_row).unsigned_abs() as usize
+    } else {
+        own_file(m.to, black)
+    };
+    Some(Description { chess_type, black, origin, direction, number })
+}
+
+/// The word telling the `i`th of `count` chesses on one file apart, from the front.
+fn position_word(i: usize, count: usize) -> char {
+    match (count, i) {
+        (_, 0) if count <= 3 => '前',
+        (3, 1) => '中',
+        (_, i) if count <= 3 && i == count - 1 => '后',
+        // four or five soldiers on one file are numbered from the front.
+        (_, i) => CHINESE_NUMERALS[i],
+    }
+}
+
+/// The move in the Chinese notation, from the point of view of the camp which plays it.
+///
+/// `chesses` is the board before the move. A face-down jieqi chess is named by the type
+/// it moves as.
+pub fn chinese(chesses: &[ChessPiece; 90], m: &Move) -> String {
+    let d = match describe(chesses, m) {
+        Some(d) => d,
+        None => return String::new(),
+    };
+    let mut result = String::new();
+    match d.origin {
+        Origin::File(file) => {
+            result.push(notation_name(d.chess_type, d.black));
+            result.push(numeral(file, d.black));
+        }
+        Origin::Tandem { index, count, file } => {
+            result.push(position_word(index, count));
+            // soldiers crowding more than one file keep the file instead of the name.
+            match file {
+                Some(file) => result.push(numeral(file, d.black)),
+                None => result.push(notation_name(d.chess_type, d.black)),
+            }
+        }
+    }
+    result.push(match d.direction {
+        Direction::Forward => '进',
+        Direction::Backward => '退',
+        Direction::Sideways => '平',
+    });
+    result.push(numeral(d.number, d.black));
+    result
+}
+
+fn wxf_letter(chess_type: ChessType) -> char {
+    match chess_type {
+        ChessType::JIANG => 'K',
+        ChessType::SHI => 'A',
+        ChessType::XIANG => 'E',
+        ChessType::MA => 'H',
+        ChessType::CHE => 'R',
+        ChessType::PAO => 'C',
+        ChessType::BING => 'P',
+    }
+}
+
+/// The move in WXF notation, like `C2.5`; `chesses` is the board before the move.
+pub fn wxf(chesses: &[ChessPiece; 90], m: &Move) -> String {
+    let d = match describe(chesses, m) {
+        Some(d) => d,
+        None => return String::new(),
+    };
+    let letter = wxf_letter(d.chess_type);
+    let origin = match d.origin {
+        Origin::File(file) => format!("{letter}{file}"),
+        Origin::Tandem { index, count: 2, file: None } => {
+            format!("{letter}{}", if index == 0 { '+' } else { '-' })
+        }
+        Origin::Tandem { index, count: _, file: None } => format!("{}{letter}", index + 1),
+        Origin::Tandem { index, count: _, file: Some(file) } => format!("{}{letter}{file}", index + 1),
+    };
+    let direction = match d.direction {
+        Direction::Forward => '+',
+        Direction::Backward => '-',
+        Direction::Sideways => '.',
+    };
+    format!("{origin}{direction}{}", d.number)
+}
+
+/// Fold the many ways of writing the same move to one, so descriptions compare equal.
+fn normalize(text: &str) -> String {
+    text.chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| match c {
+            '帅' | '帥' | '将' | '將' => '将',
+            '仕' | '士' => '士',
+            '相' | '象' => '象',
+            '马' | '馬' | '傌' => '马',
+            '车' | '車' | '俥' => '车',
+            '炮' | '砲' | '包' => '炮',
+            '兵' | '卒' => '卒',
+            '后' | '後' => '后',
+            '进' | '進' => '进',
+            '１'..='９' => char::from_u32(c as u32 - '１' as u32 + '1' as u32).unwrap_or(c),
+            c => match CHINESE_NUMERALS.iter().position(|&numeral| numeral == c) {
+                Some(n) => (b'1' + n as u8) as char,
+                None => c,
+            },
+        })
+        .collect()
+}
+
+/// Read a move in the Chinese notation, which has to be legal on the board.
+///
+/// Traditional characters, the names of either camp and either kind of numerals are accepted.
+pub fn parse_chinese(chess: &ChessInfo, text: &str) -> Result<Move, NotationError> {
+    if normalize(text).chars().count() != 4 {
+        return Err(NotationError::Syntax(text.to_string()));
+    }
+    find(chess, text, chinese, normalize)
+}
+
+/// Fold the letters of WXF to one way of writing them.
+fn normalize_wxf(text: &str) -> String {
+    text.chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| match c.to_ascii_uppercase() {
+            'B' => 'E',
+            'N' => 'H',
+            'G' => 'K',
+            '=' => '.',
+            c => c,
+        })
+        .collect()
+}
+
+/// Read a move in WXF notation, which has to be legal on the board.
+///
+/// Letters in either case, `B`/`N`/`G` for the elephant, the horse and the general, and `=` for
+/// a sideways move are accepted too.
+pub fn parse_wxf(chess: &ChessInfo, text: &str) -> Result<Move, NotationError> {
+    let wanted = normalize_wxf(text);
+    if !(4..=5).contains(&wanted.len()) || !wanted.chars().all(|c| "KAEHRCP+-.123456789".contains(c)) {
+        return Err(NotationError::Syntax(text.to_string()));
+    }
+    find(chess, text, wxf, normalize_wxf)
+}
+
+/// The move of the camp to move which `write` writes as `text`, once both are normalized.
+fn find(
+    chess: &ChessInfo,
+    text: &str,
+    write: fn(&[ChessPiece; 90], &Move) -> String,
+    normalize: fn(&str) -> String,
+) -> Result<Move, NotationError> {
+    let wanted = normalize(text);
+    let chesses = chess.chesses();
+    let black = chess.side_to_move();
+    for from in Square::all() {
+        match chesses[from.index()] {
+            ChessPiece::Chess { chess_type: _, black: b, .. } if b == black => (),
+            _ => continue,
+        }
+        for to in calculate_operators(chesses, from).unwrap_or_default() {
+            if normalize(&write(chesses, &Move::new(chesses, from, to))) == wanted {
+                return legal_move(chess, from, to);
+            }
+        }
+    }
+    Err(NotationError::NoSuchMove(text.to_string()))
+}
+
+/// The notations a move can be written in.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Notation {
+    /// Coordinates like `h2e2`.
+    Iccs,
+    /// Latin letters like `C2.5`.
+    Wxf,
+    /// Chinese like 炮二平五.
+    #[default]
+    Chinese,
+}
+
+impl Notation {
+    /// Write the move, `chesses` being the board before it.
+    pub fn write(self, chesses: &[ChessPiece; 90], m: &Move) -> String {
+        match self {
+            Notation::Iccs => iccs(m),
+            Notation::Wxf => wxf(chesses, m),
+            Notation::Chinese => chinese(chesses, m),
+        }
+    }
+
+    /// Read a move, which has to be legal on the board.
+    pub fn parse(self, chess: &ChessInfo, text: &str) -> Result<Move, NotationError> {
+        match self {
+            Notation::Iccs => parse_iccs(chess, text),
+            Notation::Wxf => parse_wxf(chess, text),
+            Notation::Chinese => parse_chinese(chess, text),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::xiangqi::legal_moves;
+
+    /// The move `text`, in ICCS, written by `write` on the position.
+    fn written(fen: &str, text: &str, write: fn(&[ChessPiece; 90], &Move) -> String) -> String {
+        let chess = ChessInfo::from_fen(fen).unwrap();
+        let m = parse_iccs(&chess, text).unwrap();
+        write(chess.chesses(), &m)
+    }
+
+    fn chinese_of(fen: &str, text: &str) -> String {
+        written(fen, text, chinese)
+    }
+
+    fn wxf_of(fen: &str, text: &str) -> String {
+        written(fen, text, wxf)
+    }
+
+    /// Positions with every kind of Chinese description, the opening included.
+    const POSITIONS: [&str; 6] = [
+        "rnbakabnr/9/1c5c1/p1p1p1p1p/9/9/P1P1P1P1P/1C5C1/9/RNBAKABNR w - - 0 1",
+        "rnbakab1r/9/1c4nc1/p1p1p1p1p/9/9/P1P1P1P1P/1C2C4/9/RNBAKABNR w - - 2 2",
+        "3k5/9/2R6/9/9/9/2R6/9/9/4K4 w - - 0 1",
+        "5k3/9/2P6/2P6/2P6/9/9/9/9/4K4 w - - 0 1",
+        "5k3/9/9/2P3P2/2P3P2/9/9/9/9/4K4 w - - 0 1",
+        "2bak4/9/3a5/9/2b6/6B2/9/5A3/9/3K1AB2 w - - 0 1",
+    ];
+
+    #[test]
+    fn iccs_dash() {
+        let squares = |from: &str, to: &str| Ok((from.parse().unwrap(), to.parse().unwrap()));
+        assert_eq!(iccs_squares("h2e2"), squares("h2", "e2"));
+        assert_eq!(iccs_squares(" H2-E2 "), squares("h2", "e2"));
+        for text in ["h-2e-2", "-h2e2-", "h2e2-", "h2--e2", "h2e", "h2e23"] {
+            assert_eq!(iccs_squares(text), Err(NotationError::Syntax(text.to_string())));
+        }
+    }
+
+    #[test]
+    fn illegal_iccs_moves() {
+        let square = |text: &str| -> Square { text.parse().unwrap() };
+        let opening = ChessInfo::from_fen(POSITIONS[0]).unwrap();
+        assert_eq!(parse_iccs(&opening, "e5e6").err(), Some(NotationError::EmptySquare(square("e5"))));
+        assert_eq!(parse_iccs(&opening, "h9g7").err(), Some(NotationError::NotToMove(square("h9"))));
+        assert_eq!(
+            parse_iccs(&opening, "b0b2").err(),
+            Some(NotationError::Unreachable { from: square("b0"), to: square("b2") })
+        );
+        // the chariot is pinned on the file of its general.
+        let pinned = ChessInfo::from_fen("3kr4/9/9/9/9/9/4R4/9/9/4K4 w").unwrap();
+        assert_eq!(
+            parse_iccs(&pinned, "e3d3").err(),
+            Some(NotationError::SelfCheck { from: square("e3"), to: square("d3") })
+        );
+        assert!(parse_iccs(&pinned, "e3e9").is_ok());
+    }
+
+    #[test]
+    fn chinese_descriptions() {
+        assert_eq!(chinese_of(POSITIONS[0], "h2e2"), "炮二平五");
+        assert_eq!(chinese_of("rnbakabnr/9/1c5c1/p1p1p1p1p/9/9/P1P1P1P1P/1C2C4/9/RNBAKABNR b", "h9g7"), "马8进7");
+        assert_eq!(chinese_of(POSITIONS[2], "c7c5"), "前车退二");
+        assert_eq!(chinese_of(POSITIONS[2], "c3d3"), "后车平六");
+        // three soldiers on one file.
+        assert_eq!(chinese_of(POSITIONS[3], "c7c8"), "前兵进一");
+        assert_eq!(chinese_of(POSITIONS[3], "c6b6"), "中兵平八");
+        assert_eq!(chinese_of(POSITIONS[3], "c5d5"), "后兵平六");
+        // doubled soldiers on two files keep the file.
+        assert_eq!(chinese_of(POSITIONS[4], "c6c7"), "前七进一");
+        assert_eq!(chinese_of(POSITIONS[4], "c5d5"), "后七平六");
+        assert_eq!(chinese_of(POSITIONS[4], "g5h5"), "后三平二");
+        // advisors and elephants sharing a file keep it.
+        assert_eq!(chinese_of(POSITIONS[5], "f2e1"), "仕四退五");
+        assert_eq!(chinese_of(POSITIONS[5], "f0e1"), "仕四进五");
+        assert_eq!(chinese_of(POSITIONS[5], "g0e2"), "相三进五");
+        assert_eq!(chinese_of(POSITIONS[5], "g4e2"), "相三退五");
+        let black = POSITIONS[5].replace(" w ", " b ");
+        assert_eq!(chinese_of(&black, "d7e8"), "士4退5");
+        assert_eq!(chinese_of(&black, "c5e7"), "象3退5");
+        let chess = ChessInfo::from_fen(POSITIONS[5]).unwrap();
+        for (text, iccs) in [("仕四退五", "f2e1"), ("仕四进五", "f0e1"), ("相三进五", "g0e2"), ("相三退五", "g4e2")] {
+            let m = parse_chinese(&chess, text).unwrap();
+            assert_eq!(format!("{}{}", m.from, m.to), iccs);
+        }
+    }
+
+    #[test]
+    fn wxf_descriptions() {
+        assert_eq!(wxf_of(POSITIONS[0], "h2e2"), "C2.5");
+        assert_eq!(wxf_of("rnbakabnr/9/1c5c1/p1p1p1p1p/9/9/P1P1P1P1P/1C2C4/9/RNBAKABNR b", "h9g7"), "H8+7");
+        assert_eq!(wxf_of(POSITIONS[2], "c7c5"), "R+-2");
+        assert_eq!(wxf_of(POSITIONS[2], "c3d3"), "R-.6");
+        assert_eq!(wxf_of(POSITIONS[3], "c7c8"), "1P+1");
+        assert_eq!(wxf_of(POSITIONS[3], "c6b6"), "2P.8");
+        assert_eq!(wxf_of(POSITIONS[3], "c5d5"), "3P.6");
+        assert_eq!(wxf_of(POSITIONS[4], "c6c7"), "1P7+1");
+        assert_eq!(wxf_of(POSITIONS[4], "c5d5"), "2P7.6");
+        assert_eq!(wxf_of(POSITIONS[4], "g5h5"), "2P3.2");
+        // advisors and elephants keep their file, the direction tells them apart.
+        assert_eq!(wxf_of(POSITIONS[5], "f2e1"), "A4-5");
+        assert_eq!(wxf_of(POSITIONS[5], "f0e1"), "A4+5");
+        assert_eq!(wxf_of(POSITIONS[5], "g0e2"), "E3+5");
+        assert_eq!(wxf_of(POSITIONS[5], "g4e2"), "E3-5");
+        let chess = ChessInfo::from_fen(POSITIONS[5]).unwrap();
+        for (text, iccs) in [("A4-5", "f2e1"), ("a4+5", "f0e1"), ("E3+5", "g0e2"), ("B3-5", "g4e2")] {
+            let m = parse_wxf(&chess, text).unwrap();
+            assert_eq!(format!("{}{}", m.from, m.to), iccs);
+        }
+    }
+
+    /// Every legal move of either camp in the positions reads back from how `write` writes it.
+    fn round_trip(
+        write: fn(&[ChessPiece; 90], &Move) -> String,
+        parse: fn(&ChessInfo, &str) -> Result<Move, NotationError>,
+    ) {
+        for fen in POSITIONS {
+            for black in [false, true] {
+                let mut chess = ChessInfo::from_fen(fen).unwrap();
+                chess.set_side_to_move(black);
+                for m in legal_moves(chess.chesses(), black) {
+                    let text = write(chess.chesses(), &m);
+                    let read = parse(&chess, &text).unwrap_or_else(|e| panic!("{fen} {text}: {e}"));
+                    assert_eq!((read.from, read.to), (m.from, m.to), "{fen} {text}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn chinese_round_trip() {
+        round_trip(chinese, parse_chinese);
+    }
+
+    #[test]
+    fn wxf_round_trip() {
+        round_trip(wxf, parse_wxf);
+    }
+}
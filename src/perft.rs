@@ -0,0 +1,265 @@
+//! Perft: counting the leaf nodes of the legal move tree, to verify the move generators. 
+//! 
+//! Any generator with the signature of [`calculate_operators`] can be counted, so the bitboard 
+//! generator and the reference one in [`moves`] can be checked against each other as well as 
+//! against the published counts of [`references`]. 
+
+use crate::ChessInfo;
+use crate::square::{Move, Square};
+use crate::xiangqi::{ChessPiece, ChessType, Hidden, RulesError, calculate_operators, find_jiang, moves};
+
+/// A pseudo-legal move generator: the targets of the chess on the given square. 
+pub type Generator = fn(&[ChessPiece; 90], Square) -> Result<Vec<Square>, RulesError>;
+
+/// The legal moves of the camp `black`, judged with `generator` alone. 
+fn legal_moves(chesses: &[ChessPiece; 90], black: bool, generator: Generator) -> Result<Vec<Move>, RulesError> {
+    let mut result = Vec::new(); 
+    for from in Square::all() {
+        match chesses[from.index()] {
+            ChessPiece::Chess { chess_type: _, black: chess_camp, .. } if chess_camp == black => (), 
+            _ => continue, 
+        }
+        for to in generator(chesses, from)? {
+            let m = Move::new(chesses, from, to); 
+            let mut next = *chesses; 
+            m.apply(&mut next); 
+            let jiang = match find_jiang(&next, black) {
+                Some(jiang) => jiang, 
+                None => continue, 
+            }; 
+            let mut checked = false; 
+            for square in Square::all() {
+                match next[square.index()] {
+                    ChessPiece::Chess { chess_type: _, black: chess_camp, .. } if chess_camp != black => {
+                        checked |= generator(&next, square)?.contains(&jiang); 
+                    }
+                    _ => (), 
+                }
+            }
+            if !checked {
+                result.push(m); 
+            }
+        }
+    }
+    Ok(result) 
+}
+
+/// Count the leaf nodes `depth` plies below the position, with `black` to move. 
+pub fn perft_with(chesses: &[ChessPiece; 90], black: bool, depth: u32, generator: Generator) -> Result<u64, RulesError> {
+    if depth == 0 {
+        return Ok(1); 
+    }
+    let moves = legal_moves(chesses, black, generator)?; 
+    if depth == 1 {
+        return Ok(moves.len() as u64); 
+    }
+    moves.into_iter().map(|m| {
+        let mut next = *chesses; 
+        m.apply(&mut next); 
+        perft_with(&next, !black, depth - 1, generator)
+    }).sum()
+}
+
+/// [`perft_with`] the bitboard generator. 
+pub fn perft(chesses: &[ChessPiece; 90], black: bool, depth: u32) -> Result<u64, RulesError> {
+    perft_with(chesses, black, depth, calculate_operators)
+}
+
+/// Break the perft count down per root move. 
+pub fn divide(chesses: &[ChessPiece; 90], black: bool, depth: u32) -> Result<Vec<(Move, u64)>, RulesError> {
+    if depth == 0 {
+        return Ok(Vec::new()); 
+    }
+    legal_moves(chesses, black, calculate_operators)?.into_iter().map(|m| {
+        let mut next = *chesses; 
+        m.apply(&mut next); 
+        Ok((m, perft(&next, !black, depth - 1)?))
+    }).collect()
+}
+
+/// A position with its published perft counts, from depth 1 on. 
+pub struct Reference {
+    pub name: &'static str, 
+    /// `(index into the board array, chess type, black)` of every chess on the board. 
+    pub pieces: Vec<(usize, ChessType, bool)>, 
+    /// The camp to move. 
+    pub black: bool, 
+    /// The published counts from depth 1 on; empty for a position without any, checked by 
+    /// comparing the generators only. 
+    pub counts: &'static [u64], 
+}
+
+impl Reference {
+    pub fn chesses(&self) -> [ChessPiece; 90] {
+        let mut chesses = [ChessPiece::None; 90]; 
+        for &(index, chess_type, black) in self.pieces.iter() {
+            chesses[index] = ChessPiece::Chess { chess_type, black, hidden: Hidden::Never }; 
+        }
+        chesses 
+    }
+}
+
+/// The chesses of the board as [`Reference::pieces`] lists them. 
+fn pieces(chesses: &[ChessPiece; 90]) -> Vec<(usize, ChessType, bool)> {
+    chesses.iter().enumerate()
+        .filter_map(|(index, piece)| match *piece {
+            ChessPiece::Chess { chess_type, black, .. } => Some((index, chess_type, black)), 
+            ChessPiece::None => None, 
+        })
+        .collect()
+}
+
+/// A reference position written as a FEN. 
+fn from_fen(name: &'static str, fen: &str, counts: &'static [u64]) -> Reference {
+    let chess = ChessInfo::from_fen(fen).expect("the references are valid FENs"); 
+    Reference { name, pieces: pieces(chess.chesses()), black: chess.side_to_move(), counts }
+}
+
+/// The opening position set up by `ChessInfo::reset` and the middle game positions of the 
+/// chessprogramming.org xiangqi perft results, with their published counts; then a few tricky 
+/// positions without any: blocked horse legs and elephant eyes, elephants on the river bank, 
+/// a pinned piece between the generals, and soldiers across the river. Those are checked by 
+/// comparing the bitboard generator with the reference generator in [`moves`]. 
+pub fn references() -> Vec<Reference> {
+    use ChessType::*; 
+    vec![
+        Reference {
+            name: "opening", 
+            pieces: pieces(ChessInfo::new().chesses()), 
+            black: false, 
+            counts: &[44, 1920, 79666, 3290240], 
+        }, 
+        from_fen(
+            "cannons and horses", 
+            "r1ba1a3/4kn3/2n1b4/pNp1p1p1p/4c4/6P2/P1P2R2P/1CcC5/9/2BAKAB2 w - - 0 1", 
+            &[38, 1128, 43929, 1339047], 
+        ), 
+        from_fen(
+            "general on the run", 
+            "1cbak4/9/n2a5/2p1p3p/5cp2/2n2N3/6PCP/3AB4/2C6/3A1K1N1 w - - 0 1", 
+            &[7, 281, 8620, 326201], 
+        ), 
+        from_fen(
+            "open palaces", 
+            "5a3/3k5/3aR4/9/5r3/5n3/9/3A1A3/5K3/2BC2B2 w - - 0 1", 
+            &[25, 424, 9850, 202884], 
+        ), 
+        from_fen(
+            "soldiers in the palace", 
+            "1C2ka3/9/C1Nab1n2/p3p3p/6p2/9/P3P3P/3AB4/3p2c2/c1BAK4 w - - 0 1", 
+            &[30, 830, 22787, 649866], 
+        ), 
+        Reference {
+            name: "horse legs", 
+            pieces: vec![
+                (4, JIANG, false), (3, SHI, false), (13, SHI, false), 
+                (40, MA, false), (49, BING, true), (39, PAO, false), 
+                (30, MA, false), (29, BING, false), (22, MA, true), 
+                (84, JIANG, true), (86, SHI, true), (76, SHI, true), 
+                (59, MA, true), (68, CHE, true), (58, PAO, false), 
+            ], 
+            black: false, 
+            counts: &[], 
+        }, 
+        Reference {
+            name: "elephants on the river bank", 
+            pieces: vec![
+                (4, JIANG, false), (38, XIANG, false), (42, XIANG, false), 
+                (31, BING, false), (27, CHE, false), 
+                (85, JIANG, true), (47, XIANG, true), (51, XIANG, true), 
+                (32, MA, true), (59, CHE, true), (54, BING, true), 
+            ], 
+            black: true, 
+            counts: &[], 
+        }, 
+        Reference {
+            name: "pinned between the generals", 
+            pieces: vec![
+                (13, JIANG, false), (31, MA, false), (3, SHI, false), 
+                (76, JIANG, true), (58, PAO, true), (84, SHI, true), 
+                (18, CHE, false), (63, CHE, true), (45, PAO, false), 
+                (69, PAO, true), 
+            ], 
+            black: false, 
+            counts: &[], 
+        }, 
+        Reference {
+            name: "soldiers across the river", 
+            pieces: vec![
+                (3, JIANG, false), (86, JIANG, true), 
+                (66, BING, false), (76, BING, false), (58, BING, false), 
+                (20, BING, true), (15, BING, true), (32, BING, true), 
+                (75, CHE, true), (13, SHI, false), 
+            ], 
+            black: true, 
+            counts: &[], 
+        }, 
+    ]
+}
+
+/// Run every reference position up to `max_depth` with both generators, comparing them with 
+/// each other and with the published counts; returns a description of every disagreement. 
+pub fn verify(max_depth: u32) -> Vec<String> {
+    let mut problems = Vec::new(); 
+    for reference in references() {
+        let chesses = reference.chesses(); 
+        for depth in 1..=max_depth {
+            let bitboard = perft(&chesses, reference.black, depth); 
+            let by_hand = perft_with(&chesses, reference.black, depth, moves::calculate); 
+            let (bitboard, by_hand) = match (bitboard, by_hand) {
+                (Ok(bitboard), Ok(by_hand)) => (bitboard, by_hand), 
+                (Err(e), _) | (_, Err(e)) => {
+                    problems.push(format!("{} at depth {depth}: {e}", reference.name)); 
+                    continue; 
+                }
+            }; 
+            if bitboard != by_hand {
+                problems.push(format!("{} at depth {depth}: bitboards {bitboard}, by hand {by_hand}", reference.name)); 
+            }
+            if let Some(&expected) = reference.counts.get(depth as usize - 1) {
+                if bitboard != expected {
+                    problems.push(format!("{} at depth {depth}: {bitboard}, expected {expected}", reference.name)); 
+                }
+            }
+        }
+    }
+    problems 
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*; 
+
+    #[test]
+    fn published_counts() {
+        let published: Vec<Reference> = references().into_iter()
+            .filter(|reference| !reference.counts.is_empty())
+            .collect(); 
+        assert_eq!(published.len(), 5); 
+        for reference in published {
+            let chesses = reference.chesses(); 
+            for depth in 1..=3 {
+                assert_eq!(
+                    perft(&chesses, reference.black, depth), 
+                    Ok(reference.counts[depth as usize - 1]), 
+                    "{} at depth {depth}", reference.name, 
+                ); 
+            }
+        }
+    }
+
+    /// Covers the blocked horse legs and the elephants on the river bank among the others. 
+    #[test]
+    fn generators_agree() {
+        for reference in references() {
+            let chesses = reference.chesses(); 
+            for depth in 1..=3 {
+                assert_eq!(
+                    perft_with(&chesses, reference.black, depth, calculate_operators), 
+                    perft_with(&chesses, reference.black, depth, moves::calculate), 
+                    "{} at depth {depth}", reference.name, 
+                ); 
+            }
+        }
+    }
+}
@@ -0,0 +1,476 @@
+//! Whole games saved as xiangqi PGN: tag pairs, then the moves and the result.
+//!
+//! ```text
+//! [Event "?"]
+//! [Site "?"]
+//! [Date "????.??.??"]
+//! [Red "?"]
+//! [Black "?"]
+//! [Result "*"]
+//! [FEN "rnbakabnr/9/1c5c1/p1p1p1p1p/9/9/P1P1P1P1P/1C5C1/9/RNBAKABNR w - - 0 1"]
+//! [Format "ICCS"]
+//!
+//! 1. h2e2 h9g7 2. h0g2 *
+//! ```
+//!
+//! `Format` names the notation of the moves, `ICCS`, `WXF` or `Chinese`; without it the moves
+//! are taken for ICCS. Without `FEN` the game starts from the opening. A handicap game has a
+//! `Handicap` tag written like its label, such as `红方让双车2先`, so the extra moves of one camp
+//! are replayed in a row. Comments, variations and annotation glyphs are skipped when reading.
+//! A game which ends without its moves settling it, by resignation for example, takes its result
+//! from the `Result` tag.
+//!
+//! Jieqi games can't be written down: FEN has no letters for face-down chesses.
+
+use crate::fen::FenError;
+use crate::notation::{Notation, NotationError};
+use crate::validation::{Problem, validate};
+use crate::{ChessInfo, GameController, GameState, Handicap};
+use crate::xiangqi::camp_name;
+
+/// The tag pairs telling what the game was.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Headers {
+    pub event: String,
+    pub site: String,
+    /// Written like `2024.05.01`, with `?` for the unknown digits.
+    pub date: String,
+    pub red: String,
+    pub black: String,
+}
+
+impl Default for Headers {
+    fn default() -> Self {
+        Headers {
+            event: "?".to_string(),
+            site: "?".to_string(),
+            date: "????.??.??".to_string(),
+            red: "?".to_string(),
+            black: "?".to_string(),
+        }
+    }
+}
+
+/// Why a text can't be read as a game.
+#[derive(Clone, PartialEq, Debug)]
+pub enum PgnError {
+    /// A line starting with `[` isn't a tag pair like `[Event "?"]`.
+    Tag(String),
+    /// The `Format` tag names no known notation.
+    Format(String),
+    /// The `FEN` tag isn't a FEN.
+    Fen(FenError),
+    /// The position of the `FEN` tag couldn't come up in a game.
+    Position(Vec<Problem>),
+    /// The move of the given ply, counted from 1, can't be played.
+    Move { ply: usize, text: String, error: NotationError },
+    /// More moves follow the end of the game.
+    AfterEnd { ply: usize, text: String },
+    /// The `Handicap` tag isn't written like a handicap label.
+    Handicap(String),
+    /// The `Result` tag isn't `1-0`, `0-1`, `1/2-1/2` or `*`, or the moves end the game otherwise.
+    Result(String),
+    /// A jieqi game, which PGN can't record.
+    Jieqi,
+}
+
+impl std::fmt::Display for PgnError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PgnError::Tag(line) => write!(f, "无效的标签：{line}"),
+            PgnError::Format(format) => write!(f, "未知的记谱格式：{format}"),
+            PgnError::Fen(e) => write!(f, "{e}"),
+            PgnError::Position(problems) => {
+                let problems: Vec<String> = problems.iter().map(|problem| problem.to_string()).collect();
+                write!(f, "{}", problems.join("，"))
+            }
+            PgnError::Move { ply, text, error } => write!(f, "第{ply}步{text}：{error}"),
+            PgnError::AfterEnd { ply, text } => write!(f, "第{ply}步{text}：对局已经结束"),
+            PgnError::Handicap(value) => write!(f, "无效的让子：{value}"),
+            PgnError::Result(value) => write!(f, "无效的结果：{value}"),
+            PgnError::Jieqi => write!(f, "揭棋对局无法保存为PGN"),
+        }
+    }
+}
+
+impl std::error::Error for PgnError {}
+
+impl From<FenError> for PgnError {
+    fn from(e: FenError) -> Self {
+        PgnError::Fen(e)
+    }
+}
+
+/// A game read back from PGN, played up to its last move.
+pub struct Game {
+    pub headers: Headers,
+    pub chess: ChessInfo,
+    pub game: GameController,
+}
+
+fn format_name(notation: Notation) -> &'static str {
+    match notation {
+        Notation::Iccs => "ICCS",
+        Notation::Wxf => "WXF",
+        Notation::Chinese => "Chinese",
+    }
+}
+
+fn result_token(state: &GameState) -> &'static str {
+    match state {
+        GameState::Win { black: false } => "1-0",
+        GameState::Win { black: true } => "0-1",
+        GameState::Draw => "1/2-1/2",
+        GameState::Playing(_) => "*",
+    }
+}
+
+/// The state a `Result` tag stands for, `None` for a game going on.
+fn result_state(value: &str) -> Result<Option<GameState>, PgnError> {
+    match value {
+        "1-0" => Ok(Some(GameState::Win { black: false })),
+        "0-1" => Ok(Some(GameState::Win { black: true })),
+        "1/2-1/2" => Ok(Some(GameState::Draw)),
+        "*" => Ok(None),
+        _ => Err(PgnError::Result(value.to_string())),
+    }
+}
+
+/// Read a handicap written like `红方让双车单马2先`, the camp then its label.
+fn handicap(value: &str) -> Result<Handicap, PgnError> {
+    let error = || PgnError::Handicap(value.to_string());
+    let (black, rest) = match (value.strip_prefix(camp_name(false)), value.strip_prefix(camp_name(true))) {
+        (Some(rest), _) => (false, rest),
+        (_, Some(rest)) => (true, rest),
+        _ => return Err(error()),
+    };
+    let mut h = Handicap { black, ..Handicap::default() };
+    let mut chars = rest.strip_prefix('让').ok_or_else(error)?.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        let count = match c {
+            '单' => 1,
+            '双' => 2,
+            _ => break,
+        };
+        chars.next();
+        match chars.next() {
+            Some('车') => h.chariots = count,
+            Some('马') => h.horses = count,
+            Some('炮') => h.cannons = count,
+            _ => return Err(error()),
+        }
+    }
+    let rest: String = chars.collect();
+    h.extra_moves = match rest.as_str() {
+        "" | "子" => 0,
+        "先" => 1,
+        rest => rest.strip_suffix('先').and_then(|n| n.parse().ok()).ok_or_else(error)?,
+    };
+    Ok(h)
+}
+
+/// Escape a tag value for the quotes around it.
+fn quote(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Write the game played on `chess` so far, the moves in the notation.
+pub fn write(headers: &Headers, chess: &ChessInfo, game: &GameController, notation: Notation) -> Result<String, PgnError> {
+    // take every operator back on a copy to find the start position.
+    let mut board = chess.clone();
+    for undo in game.operators.iter().rev() {
+        board.unmake_move(*undo);
+    }
+    if board.is_jieqi() {
+        return Err(PgnError::Jieqi);
+    }
+    let result = result_token(&game.state);
+    let handicap = format!("{}{}", camp_name(game.handicap.black), game.handicap);
+    let mut tags = vec![
+        ("Event", headers.event.as_str()),
+        ("Site", headers.site.as_str()),
+        ("Date", headers.date.as_str()),
+        ("Red", headers.red.as_str()),
+        ("Black", headers.black.as_str()),
+        ("Result", result),
+    ];
+    if !game.handicap.is_none() {
+        tags.push(("Handicap", handicap.as_str()));
+    }
+    let fen = board.fen();
+    tags.push(("FEN", fen.as_str()));
+    tags.push(("Format", format_name(notation)));
+    let mut text = String::new();
+    for (tag, value) in tags {
+        text.push_str(&format!("[{tag} \"{}\"]\n", quote(value)));
+    }
+    text.push('\n');
+
+    // replay on the copy, so the extra moves of a handicap keep their camp.
+    let mut replay = GameController::new();
+    replay.handicap = game.handicap;
+    let mut tokens = Vec::new();
+    let mut last_black = None;
+    for undo in game.operators.iter() {
+        let m = undo.movement();
+        let black = board.side_to_move();
+        if !black {
+            tokens.push(format!("{}.", board.fullmove()));
+        } else if last_black != Some(false) {
+            // black moving first, or once more in a row.
+            tokens.push(format!("{}...", board.fullmove()));
+        }
+        last_black = Some(black);
+        tokens.push(notation.write(board.chesses(), m));
+        replay.play(&mut board, *m);
+    }
+    tokens.push(result.to_string());
+
+    // lines of at most 80 characters.
+    let mut line = String::new();
+    for token in tokens {
+        if !line.is_empty() && line.chars().count() + 1 + token.chars().count() > 80 {
+            text.push_str(&line);
+            text.push('\n');
+            line.clear();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(&token);
+    }
+    text.push_str(&line);
+    text.push('\n');
+    Ok(text)
+}
+
+/// Read the tag pair of a line like `[Event "?"]`.
+fn tag(line: &str) -> Result<(String, String), PgnError> {
+    let error = || PgnError::Tag(line.to_string());
+    let inner = line.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')).ok_or_else(error)?;
+    let (name, value) = inner.split_once(char::is_whitespace).ok_or_else(error)?;
+    let value = value.trim().strip_prefix('"').and_then(|rest| rest.strip_suffix('"')).ok_or_else(error)?;
+    let mut unescaped = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unescaped.extend(chars.next()),
+            c => unescaped.push(c),
+        }
+    }
+    Ok((name.to_string(), unescaped))
+}
+
+/// The move text without comments, variations, move numbers, annotation glyphs and results.
+fn move_tokens(text: &str) -> Vec<String> {
+    let mut plain = String::new();
+    let mut comment = false;
+    let mut variation = 0;
+    let mut line_comment = false;
+    for c in text.chars() {
+        match c {
+            '\n' if line_comment => line_comment = false,
+            _ if line_comment => (),
+            '}' if comment => comment = false,
+            _ if comment => (),
+            '{' => comment = true,
+            '(' => variation += 1,
+            ')' if variation > 0 => variation -= 1,
+            _ if variation > 0 => (),
+            ';' => line_comment = true,
+            c => plain.push(c),
+        }
+    }
+    plain.split_whitespace()
+        .map(|token| {
+            // a move number may stick to its move, like `1.h2e2`.
+            let digits = token.len() - token.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            match token[digits..].trim_start_matches('.') {
+                rest if digits > 0 && rest.len() < token.len() - digits => rest,
+                _ => token,
+            }
+        })
+        .filter(|token| !token.is_empty() && !token.starts_with('$'))
+        .filter(|token| !matches!(*token, "1-0" | "0-1" | "1/2-1/2" | "*"))
+        .map(|token| token.to_string())
+        .collect()
+}
+
+/// Read a game, replaying every move after checking it's legal.
+pub fn read(text: &str) -> Result<Game, PgnError> {
+    let mut headers = Headers::default();
+    let mut fen = None;
+    let mut handicap_tag = None;
+    let mut result = None;
+    let mut notation = Notation::Iccs;
+    let mut moves = String::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if !line.starts_with('[') {
+            moves.push_str(line);
+            moves.push('\n');
+            continue;
+        }
+        let (name, value) = tag(line)?;
+        match name.as_str() {
+            "Event" => headers.event = value,
+            "Site" => headers.site = value,
+            "Date" => headers.date = value,
+            "Red" => headers.red = value,
+            "Black" => headers.black = value,
+            "FEN" => fen = Some(value),
+            "Handicap" => handicap_tag = Some(handicap(&value)?),
+            "Result" => result = result_state(&value)?,
+            "Format" => {
+                notation = match value.to_ascii_uppercase().as_str() {
+                    "ICCS" => Notation::Iccs,
+                    "WXF" => Notation::Wxf,
+                    "CHINESE" | "中文" => Notation::Chinese,
+                    _ => return Err(PgnError::Format(value)),
+                };
+            }
+            _ => (),
+        }
+    }
+
+    let handicap = handicap_tag.unwrap_or_default();
+    let mut chess = match fen {
+        Some(fen) => ChessInfo::from_fen(&fen)?,
+        None => {
+            let mut chess = ChessInfo::new();
+            chess.reset_handicap(&handicap);
+            chess
+        }
+    };
+    let problems = validate(&chess, chess.side_to_move());
+    if !problems.is_empty() {
+        return Err(PgnError::Position(problems));
+    }
+    let mut game = GameController::new();
+    game.handicap = handicap;
+    game.settle(&chess);
+    for (i, text) in move_tokens(&moves).into_iter().enumerate() {
+        let ply = i + 1;
+        if !matches!(game.state, GameState::Playing(_)) {
+            return Err(PgnError::AfterEnd { ply, text });
+        }
+        match notation.parse(&chess, &text) {
+            Ok(m) => game.play(&mut chess, m),
+            Err(error) => return Err(PgnError::Move { ply, text, error }),
+        }
+    }
+    match (&game.state, result) {
+        // a resignation or an agreed draw, which the moves alone don't tell.
+        (GameState::Playing(_), Some(state)) => game.state = state,
+        (state, Some(tag)) if result_token(state) != result_token(&tag) => {
+            return Err(PgnError::Result(result_token(&tag).to_string()));
+        }
+        _ => (),
+    }
+    Ok(Game { headers, chess, game })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::xiangqi::legal_moves;
+
+    /// Play `plies` moves, picked by a fixed rule so the game is the same every run.
+    fn play(chess: &mut ChessInfo, game: &mut GameController, plies: usize) {
+        for i in 0..plies {
+            let moves = legal_moves(chess.chesses(), chess.side_to_move());
+            if moves.is_empty() || !matches!(game.state, GameState::Playing(_)) {
+                break;
+            }
+            game.play(chess, moves[(i * 7 + 3) % moves.len()]);
+        }
+    }
+
+    fn round_trip(chess: &ChessInfo, game: &GameController) {
+        for notation in [Notation::Iccs, Notation::Wxf, Notation::Chinese] {
+            let text = write(&Headers::default(), chess, game, notation).unwrap();
+            let read = read(&text).unwrap_or_else(|e| panic!("{e}\n{text}"));
+            let played: Vec<_> = game.operators.iter().map(|undo| (undo.movement().from, undo.movement().to)).collect();
+            let replayed: Vec<_> = read.game.operators.iter().map(|undo| (undo.movement().from, undo.movement().to)).collect();
+            assert_eq!(played, replayed, "{text}");
+            assert_eq!(read.chess.fen(), chess.fen(), "{text}");
+            assert_eq!(read.game.handicap, game.handicap);
+            assert_eq!(result_token(&read.game.state), result_token(&game.state));
+        }
+    }
+
+    #[test]
+    fn round_trip_from_the_opening() {
+        let mut chess = ChessInfo::new();
+        let mut game = GameController::new();
+        play(&mut chess, &mut game, 80);
+        round_trip(&chess, &game);
+    }
+
+    #[test]
+    fn round_trip_handicap() {
+        let handicap = Handicap { black: false, chariots: 1, horses: 2, cannons: 0, extra_moves: 3 };
+        let mut chess = ChessInfo::new();
+        chess.reset_handicap(&handicap);
+        let mut game = GameController::new();
+        game.handicap = handicap;
+        play(&mut chess, &mut game, 20);
+        let text = write(&Headers::default(), &chess, &game, Notation::Iccs).unwrap();
+        assert!(text.contains("[Handicap \"红方让单车双马3先\"]"), "{text}");
+        assert!(text.contains("1... ") && text.contains(" 2... ") && text.contains(" 3... "), "{text}");
+        round_trip(&chess, &game);
+    }
+
+    #[test]
+    fn handicap_tags() {
+        let h = Handicap { black: true, chariots: 2, horses: 0, cannons: 1, extra_moves: 1 };
+        assert_eq!(handicap(&format!("黑方{h}")), Ok(h));
+        assert_eq!(handicap("红方让子"), Ok(Handicap::default()));
+        assert!(matches!(handicap("让双车"), Err(PgnError::Handicap(_))));
+        assert!(matches!(handicap("红方让双兵"), Err(PgnError::Handicap(_))));
+    }
+
+    #[test]
+    fn jieqi_is_refused() {
+        let chess = ChessInfo::jieqi(7);
+        let game = GameController::new();
+        assert_eq!(write(&Headers::default(), &chess, &game, Notation::Iccs), Err(PgnError::Jieqi));
+    }
+
+    #[test]
+    fn standard_advisor_and_elephant_moves() {
+        let chinese = "[Format \"Chinese\"]\n1. 相三进五 马8进7 2. 仕四进五 车9平8 3. 马二进三 *";
+        let wxf = "[Format \"WXF\"]\n1. E3+5 H8+7 2. A4+5 R9.8 3. H2+3 *";
+        let iccs = read("1. g0e2 h9g7 2. f0e1 i9h9 3. h0g2 *").unwrap();
+        for text in [chinese, wxf] {
+            let game = read(text).unwrap_or_else(|e| panic!("{e}\n{text}"));
+            assert_eq!(game.chess.fen(), iccs.chess.fen());
+        }
+
+        // advisors and elephants sharing a file.
+        let fen = "[FEN \"2bak4/9/3a5/9/2b6/6B2/9/5A3/9/3K1AB2 w - - 0 1\"]\n";
+        let chinese = read(&format!("{fen}[Format \"Chinese\"]\n1. 仕四退五 士4退5 2. 相三进五 *")).unwrap();
+        let wxf = read(&format!("{fen}[Format \"WXF\"]\n1. A4-5 A4-5 2. E3+5 *")).unwrap();
+        let iccs = read(&format!("{fen}1. f2e1 d7e8 2. g0e2 *")).unwrap();
+        assert_eq!(chinese.chess.fen(), iccs.chess.fen());
+        assert_eq!(wxf.chess.fen(), iccs.chess.fen());
+    }
+
+    #[test]
+    fn result_against_the_board() {
+        let mate = "[FEN \"4k4/R8/9/9/9/9/9/9/9/1R1K5 w - - 0 1\"]\n";
+        let game = read(&format!("{mate}[Result \"1-0\"]\n1. b0b9 1-0")).unwrap();
+        assert!(matches!(game.game.state, GameState::Win { black: false }));
+        assert_eq!(read(&format!("{mate}[Result \"0-1\"]\n1. b0b9 0-1")).err(), Some(PgnError::Result("0-1".to_string())));
+        assert_eq!(read(&format!("{mate}[Result \"1/2-1/2\"]\n1. b0b9")).err(), Some(PgnError::Result("1/2-1/2".to_string())));
+    }
+
+    #[test]
+    fn result_of_a_resignation() {
+        let game = read("[Result \"1-0\"]\n1. h2e2 h9g7 1-0").unwrap();
+        assert!(matches!(game.game.state, GameState::Win { black: false }));
+        let game = read("[Result \"*\"]\n1. h2e2 h9g7 *").unwrap();
+        assert!(matches!(game.game.state, GameState::Playing(_)));
+        assert!(matches!(read("[Result \"2-0\"]"), Err(PgnError::Result(_))));
+    }
+}
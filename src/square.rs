@@ -0,0 +1,172 @@
+//! Positions on the 9x10 board and moves between them.
+//!
+//! A [`Square`] is stored as `row * 9 + col` of the board array: row 0 is the bottom line of the
+//! red camp (`black: false`), row 9 the one of the black camp. Files and ranks follow the usual
+//! xiangqi orientation, seen from the red side: file `a` is on red's left, rank 0 is red's bottom line.
+
+use crate::xiangqi::{ChessPiece, RulesError};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct Square(u8);
+
+impl Square {
+    pub const COUNT: usize = 90;
+
+    /// The square on the given row and column of the board array.
+    pub fn new(row: usize, col: usize) -> Option<Square> {
+        if row < 10 && col < 9 {
+            Some(Square((row * 9 + col) as u8))
+        } else {
+            None
+        }
+    }
+
+    pub fn from_index(index: usize) -> Option<Square> {
+        if index < Self::COUNT {
+            Some(Square(index as u8))
+        } else {
+            None
+        }
+    }
+
+    /// The square on `file` (0 for `a`) and `rank` (0 for red's bottom line).
+    pub fn from_file_rank(file: usize, rank: usize) -> Option<Square> {
+        if file < 9 {
+            Square::new(rank, 8 - file)
+        } else {
+            None
+        }
+    }
+
+    /// Every square, by increasing index.
+    pub fn all() -> impl Iterator<Item = Square> {
+        (0..Self::COUNT as u8).map(Square)
+    }
+
+    /// The index into the board array.
+    pub const fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    pub const fn row(self) -> usize {
+        self.0 as usize / 9
+    }
+
+    pub const fn col(self) -> usize {
+        self.0 as usize % 9
+    }
+
+    /// The file from red's left, 0 for `a` to 8 for `i`.
+    pub const fn file(self) -> usize {
+        8 - self.col()
+    }
+
+    /// The rank from red's bottom line, 0 to 9.
+    pub const fn rank(self) -> usize {
+        self.row()
+    }
+
+    /// The row counted from the bottom line of the given camp.
+    pub const fn relative_row(self, black: bool) -> usize {
+        match black {
+            false => self.row(),
+            true => 9 - self.row(),
+        }
+    }
+
+    /// Whether the square is across the river for the given camp.
+    pub const fn crossed(self, black: bool) -> bool {
+        self.relative_row(black) >= 5
+    }
+
+    /// The same square seen from the other side of the board.
+    pub const fn flip(self) -> Square {
+        Square(89 - self.0)
+    }
+
+    /// The square `d_row` rows and `d_col` columns away, if it's still on the board.
+    pub fn offset(self, d_row: i32, d_col: i32) -> Option<Square> {
+        let row = self.row() as i32 + d_row;
+        let col = self.col() as i32 + d_col;
+        if (0..10).contains(&row) && (0..9).contains(&col) {
+            Square::new(row as usize, col as usize)
+        } else {
+            None
+        }
+    }
+}
+
+/// Written as file letter and rank, like `e0` for the red general on its opening square.
+impl std::fmt::Display for Square {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", (b'a' + self.file() as u8) as char, self.rank())
+    }
+}
+
+impl std::str::FromStr for Square {
+    type Err = ();
+
+    /// Read a square written like [`Square`]'s `Display`, the file letter in either case.
+    fn from_str(s: &str) -> Result<Square, ()> {
+        match s.as_bytes() {
+            &[file, rank] => {
+                let file = file.to_ascii_lowercase().checked_sub(b'a').ok_or(())?;
+                let rank = rank.checked_sub(b'0').filter(|&rank| rank < 10).ok_or(())?;
+                Square::from_file_rank(file as usize, rank as usize).ok_or(())
+            }
+            _ => Err(()),
+        }
+    }
+}
+
+impl From<Square> for usize {
+    fn from(square: Square) -> usize {
+        square.index()
+    }
+}
+
+impl TryFrom<usize> for Square {
+    type Error = RulesError;
+    fn try_from(index: usize) -> Result<Square, RulesError> {
+        Square::from_index(index).ok_or(RulesError::OutOfRange(index))
+    }
+}
+
+/// A move of one chess, with what it captures.
+#[derive(Clone, Copy, Debug)]
+pub struct Move {
+    pub from: Square,
+    pub to: Square,
+    /// The chess which moves.
+    pub piece: ChessPiece,
+    /// The chess standing on `to` before the move, `ChessPiece::None` for a quiet move.
+    pub captured: ChessPiece,
+}
+
+impl Move {
+    /// The move of the chess on `from` to `to` on the given board.
+    pub fn new(chesses: &[ChessPiece; 90], from: Square, to: Square) -> Move {
+        Move {
+            from,
+            to,
+            piece: chesses[from.index()],
+            captured: chesses[to.index()],
+        }
+    }
+
+    pub fn is_capture(&self) -> bool {
+        !matches!(self.captured, ChessPiece::None)
+    }
+
+    /// Play the move on a board array; a face-down chess turns face up.
+    pub fn apply(&self, chesses: &mut [ChessPiece; 90]) {
+        chesses[self.to.index()] = self.piece.revealed();
+        chesses[self.from.index()] = ChessPiece::None;
+    }
+
+    /// Take the move back on a board array.
+    pub fn revert(&self, chesses: &mut [ChessPiece; 90]) {
+        chesses[self.from.index()] = self.piece;
+        chesses[self.to.index()] = self.captured;
+    }
+}
@@ -1,6 +1,12 @@
 use eframe::{epaint::Color32, App, egui::CentralPanel};
 
 use crate::MyApp;
+use crate::chess::Chess;
+use crate::banqi::Banqi;
+use crate::janggi::{Janggi, Setup};
+
+use board_game::BoardGame;
+use editor::Editor;
 
 pub struct AnimationResponse {
     pub repaint: bool, 
@@ -14,6 +20,12 @@ impl AnimationResponse {
     }
 }
 
+impl Default for AnimationResponse {
+    fn default() -> Self {
+        Self::new() 
+    }
+}
+
 pub struct ColorDisplay {
     pub foreground: Color32, 
     pub background: Color32, 
@@ -42,6 +54,12 @@ impl GameApp {
     }
 }
 
+impl Default for GameApp {
+    fn default() -> Self {
+        Self::new() 
+    }
+}
+
 pub struct User {
     private: Option<PrivateUser>,
     achievement: Achievement, 
@@ -120,7 +138,28 @@ pub enum Frame {
     MainPage, 
     NormalGame {
         // 若干属性
-        app: MyApp, 
+        app: Box<MyApp>, 
+    }, 
+    /// A hot seat game of international chess. 
+    Chess {
+        app: Box<BoardGame<Chess>>, 
+    }, 
+    /// A hot seat game of banqi (暗棋). 
+    Banqi {
+        app: Box<BoardGame<Banqi>>, 
+    }, 
+    /// Choosing where the horses and the elephants of each janggi camp start. 
+    JanggiSetup {
+        han: Setup, 
+        cho: Setup, 
+    }, 
+    /// A hot seat game of janggi. 
+    Janggi {
+        app: Box<BoardGame<Janggi>>, 
+    }, 
+    /// Setting up a position to play. 
+    Editor {
+        editor: Box<Editor>, 
     }, 
     RemoteGame(RemoteFrame),  
 }
@@ -155,6 +194,34 @@ impl App for GameApp {
             Frame::NormalGame { ref mut app } => {
                 app.update(ctx, frame); 
             }
+            Frame::Chess { ref mut app } => {
+                app.update(ctx, frame); 
+            }
+            Frame::Banqi { ref mut app } => {
+                app.update(ctx, frame); 
+            }
+            Frame::JanggiSetup { ref mut han, ref mut cho } => {
+                let mut start = false; 
+                CentralPanel::default().show(ctx, |ui| {
+                    start = main_page::janggi_setup(ui, han, cho); 
+                }); 
+                if start {
+                    let (han, cho) = (*han, *cho); 
+                    self.frame = Frame::Janggi { app: Box::new(BoardGame::new(move || Janggi::new(han, cho))) }; 
+                }
+            }
+            Frame::Janggi { ref mut app } => {
+                app.update(ctx, frame); 
+            }
+            Frame::Editor { ref mut editor } => {
+                let mut app = None; 
+                CentralPanel::default().show(ctx, |ui| {
+                    app = editor.show(ui); 
+                }); 
+                if let Some(app) = app {
+                    self.frame = Frame::NormalGame { app: Box::new(app) }; 
+                }
+            }
             Frame::RemoteGame(_) => todo!(), 
         }
     }
@@ -162,12 +229,16 @@ impl App for GameApp {
 
 pub mod main_page {
     use std::process::ExitCode;
+    use std::time::{SystemTime, UNIX_EPOCH};
 
     use eframe::{egui::{Ui, RichText, Button}, epaint::vec2};
 
     use crate::MyApp;
+    use crate::chess::Chess;
+    use crate::banqi::Banqi;
+    use crate::janggi::Setup;
 
-    use super::{GameApp, Frame};
+    use super::{GameApp, Frame, board_game::BoardGame, editor::Editor};
 
     type MyGame = GameApp; 
 
@@ -176,6 +247,11 @@ pub mod main_page {
         Exit, 
     }
 
+    /// A seed for the shuffles, from the clock. 
+    fn seed() -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos() as u64)
+    }
+
     pub fn show(game: &mut MyGame, ui: &mut Ui) -> Feedback {
         let mut result = Feedback::None; 
         ui.vertical_centered(|ui| {
@@ -189,7 +265,45 @@ pub mod main_page {
             ui.add_space(40.0);
             if ui.add(button).clicked() {
                 // balabalabala ~  
-                game.frame = Frame::NormalGame { app: MyApp::new() }; 
+                game.frame = Frame::NormalGame { app: Box::new(MyApp::new()) }; 
+                return ; 
+            }
+            let text = RichText::new("摆棋").size(40.0);
+            let button = Button::new(text).min_size(vec2(210.0, 55.0)); 
+            ui.add_space(40.0);
+            if ui.add(button).clicked() {
+                game.frame = Frame::Editor { editor: Box::new(Editor::new()) }; 
+                return ; 
+            }
+            let text = RichText::new("揭棋").size(40.0);
+            let button = Button::new(text).min_size(vec2(210.0, 55.0)); 
+            ui.add_space(40.0);
+            if ui.add(button).clicked() {
+                game.frame = Frame::NormalGame { app: Box::new(MyApp::jieqi(seed())) }; 
+                return ; 
+            }
+            let text = RichText::new("暗棋").size(40.0);
+            let button = Button::new(text).min_size(vec2(210.0, 55.0)); 
+            ui.add_space(40.0);
+            if ui.add(button).clicked() {
+                game.frame = Frame::Banqi { app: Box::new(BoardGame::new(|| Banqi::new(seed()))) }; 
+                return ; 
+            }
+            let text = RichText::new("韩国将棋").size(40.0);
+            let button = Button::new(text).min_size(vec2(210.0, 55.0)); 
+            ui.add_space(40.0);
+            if ui.add(button).clicked() {
+                game.frame = Frame::JanggiSetup {
+                    han: Setup::HorseElephantHorseElephant, 
+                    cho: Setup::HorseElephantHorseElephant, 
+                }; 
+                return ; 
+            }
+            let text = RichText::new("国际象棋").size(40.0);
+            let button = Button::new(text).min_size(vec2(210.0, 55.0)); 
+            ui.add_space(40.0);
+            if ui.add(button).clicked() {
+                game.frame = Frame::Chess { app: Box::new(BoardGame::new(Chess::new)) }; 
                 return ; 
             }
             let text = RichText::new("主机").size(40.0);
@@ -210,6 +324,28 @@ pub mod main_page {
         }); 
         result 
     }
+
+    /// Let each camp of janggi pick where its horses and elephants start; whether to start. 
+    pub fn janggi_setup(ui: &mut Ui, han: &mut Setup, cho: &mut Setup) -> bool {
+        let mut start = false; 
+        ui.vertical_centered(|ui| {
+            ui.add_space(35.0); 
+            ui.label(RichText::new("韩国将棋").size(60.0).heading()); 
+            for (camp, setup) in [("楚", cho), ("漢", han)] {
+                ui.add_space(20.0); 
+                ui.label(RichText::new(format!("{camp}方布阵")).size(30.0)); 
+                ui.horizontal(|ui| {
+                    for choice in Setup::ALL {
+                        ui.radio_value(setup, choice, RichText::new(choice.name()).size(24.0)); 
+                    }
+                }); 
+            }
+            ui.add_space(40.0); 
+            let button = Button::new(RichText::new("开始").size(40.0)).min_size(vec2(210.0, 55.0)); 
+            start = ui.add(button).clicked(); 
+        }); 
+        start 
+    }
 }
 
 pub mod hot_seat {
@@ -220,4 +356,276 @@ pub mod hot_seat {
     type MyGame = GameApp; 
     pub fn show(game: &mut MyGame, ui: &mut Ui) {
     }
-}
\ No newline at end of file
+}
+/// A hot seat game of any [`ChessBoard`], played with the mouse: click a chess, then its target. 
+pub mod board_game {
+    use eframe::{App, egui::{self, CentralPanel, RichText, Visuals}, epaint::{Color32, Stroke}};
+
+    use crate::chess::{ChessBoard, Outcome};
+
+    pub struct BoardGame<B: ChessBoard> {
+        pub board: B, 
+        /// Builds the board of a new game, for 重置棋局. 
+        create: Box<dyn Fn() -> B>, 
+        selected: Option<(usize, usize)>, 
+        /// The moves sharing the endpoints just clicked, for the player to pick one. 
+        choices: Vec<B::Move>, 
+    }
+
+    impl<B: ChessBoard> BoardGame<B> {
+        pub fn new(create: impl Fn() -> B + 'static) -> Self {
+            BoardGame {
+                board: create(), 
+                create: Box::new(create), 
+                selected: None, 
+                choices: Vec::new(), 
+            }
+        }
+
+        /// The legal moves starting from `from`. 
+        fn moves_from(&self, from: (usize, usize)) -> Vec<B::Move> {
+            self.board.legal_moves().into_iter()
+                .filter(|m| self.board.endpoints(m).0 == from)
+                .collect()
+        }
+
+        fn click(&mut self, position: (usize, usize)) {
+            // a click on the board drops a pending choice. 
+            self.choices.clear(); 
+            if let Some(from) = self.selected.take() {
+                let mut moves: Vec<B::Move> = self.moves_from(from).into_iter()
+                    .filter(|m| self.board.endpoints(m).1 == position)
+                    .collect(); 
+                match moves.len() {
+                    0 => (), 
+                    1 => {
+                        self.board.make_move(moves.remove(0)); 
+                        return ; 
+                    }
+                    _ => {
+                        self.choices = moves; 
+                        return ; 
+                    }
+                }
+                if from == position {
+                    return ; 
+                }
+            }
+            if !self.moves_from(position).is_empty() {
+                self.selected = Some(position); 
+            }
+        }
+    }
+
+    impl<B: ChessBoard> App for BoardGame<B> {
+        fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+            let mut click = None; 
+            CentralPanel::default().show(ctx, |ui| {
+                egui::menu::bar(ui, |ui| {
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
+                        let rst = match ui.visuals().dark_mode {
+                            true => Visuals::dark(), 
+                            false => Visuals::light(),
+                        } .light_dark_small_toggle_button(ui);
+                        if let Some(rst) = rst {
+                            ctx.set_visuals(rst);
+                        }
+                    });
+                }); 
+                ui.vertical_centered(|ui| {
+                    let text = match self.board.result() {
+                        Some(Outcome::Win { black }) => Some(format!("{}胜", self.board.camp_name(black))), 
+                        Some(Outcome::Draw) => Some("和棋".to_string()), 
+                        None => None, 
+                    }; 
+                    if let Some(text) = text {
+                        ui.label(RichText::new(text).size(40.0).strong()); 
+                        ui.separator(); 
+                    }
+                    let targets: Vec<(usize, usize)> = match self.selected {
+                        Some(from) => self.moves_from(from).iter().map(|m| self.board.endpoints(m).1).collect(), 
+                        None => Vec::new(), 
+                    }; 
+                    let (cols, rows) = self.board.size(); 
+                    egui::Grid::new("board game").num_columns(cols).show(ui, |ui| {
+                        for row in 0..rows {
+                            for col in 0..cols {
+                                let size = 50.0; 
+                                let (word, camp) = match self.board.piece(row, col) {
+                                    Some(piece) => self.board.glyph(piece), 
+                                    None => ("", None), 
+                                }; 
+                                let word = RichText::new(word).size(size * 0.7).color(match camp {
+                                    Some(true) => Color32::BLACK, 
+                                    Some(false) => Color32::from_rgb(200, 50, 30), 
+                                    None => Color32::from_gray(120), 
+                                }); 
+                                let mut widget = egui::Button::new(word); 
+                                if self.selected == Some((row, col)) {
+                                    widget = widget.stroke(Stroke::new(2.0, Color32::RED)); 
+                                } else if targets.contains(&(row, col)) {
+                                    widget = widget.stroke(Stroke::new(2.0, Color32::GREEN)); 
+                                }
+                                if ui.add_sized([size, size], widget).clicked() {
+                                    click = Some((row, col)); 
+                                }
+                            }
+                            ui.end_row(); 
+                        }
+                    }); 
+
+                    ui.separator(); 
+
+                    if !self.choices.is_empty() {
+                        let mut chosen = None; 
+                        ui.horizontal(|ui| {
+                            for (i, m) in self.choices.iter().enumerate() {
+                                if ui.button(RichText::new(self.board.choice(m)).size(30.0)).clicked() {
+                                    chosen = Some(i); 
+                                }
+                            }
+                            if ui.button("取消").clicked() {
+                                chosen = None; 
+                                self.choices.clear(); 
+                            }
+                        }); 
+                        if let Some(i) = chosen {
+                            let m = self.choices[i]; 
+                            self.choices.clear(); 
+                            self.board.make_move(m); 
+                        }
+                        ui.separator(); 
+                    }
+
+                    ui.horizontal(|ui| {
+                        if ui.button("悔棋").clicked() {
+                            self.board.undo_move(); 
+                            self.selected = None; 
+                            self.choices.clear(); 
+                        }
+                        if ui.button("重置棋局").clicked() {
+                            self.board = (self.create)(); 
+                            self.selected = None; 
+                            self.choices.clear(); 
+                        }
+                    }); 
+                }); 
+            }); 
+            if let Some(position) = click {
+                self.click(position); 
+                ctx.request_repaint(); 
+            }
+        }
+    }
+}
+
+/// Set up any position by hand, then play it in a hot seat game. 
+pub mod editor {
+    use eframe::{egui::{self, RichText, Ui}, epaint::Color32};
+
+    use crate::{ChessInfo, MyApp};
+    use crate::square::Square;
+    use crate::validation::{Problem, validate};
+    use crate::xiangqi::{ChessPiece, ChessType, Hidden};
+
+    pub struct Editor {
+        chesses: [ChessPiece; 90], 
+        /// The chess placed by a click, `ChessPiece::None` to remove. 
+        brush: ChessPiece, 
+        /// The camp to move once the game starts. 
+        black: bool, 
+        problems: Vec<Problem>, 
+    }
+
+    impl Editor {
+        pub fn new() -> Self {
+            Editor {
+                chesses: *ChessInfo::new().chesses(), 
+                brush: ChessPiece::None, 
+                black: false, 
+                problems: Vec::new(), 
+            }
+        }
+
+        /// Draw the editor; returns the game to play once the position is accepted. 
+        pub fn show(&mut self, ui: &mut Ui) -> Option<MyApp> {
+            let mut result = None; 
+            ui.vertical_centered(|ui| {
+                for camp in [false, true] {
+                    ui.horizontal(|ui| {
+                        for chess_type in ChessType::ALL {
+                            let piece = ChessPiece::Chess { chess_type, black: camp, hidden: Hidden::Never }; 
+                            let selected = matches!(self.brush, ChessPiece::Chess { chess_type: t, black: b, .. } if t == chess_type && b == camp); 
+                            if ui.selectable_label(selected, piece_text(piece, 30.0)).clicked() {
+                                self.brush = piece; 
+                            }
+                        }
+                    }); 
+                }
+                if ui.selectable_label(matches!(self.brush, ChessPiece::None), "移除").clicked() {
+                    self.brush = ChessPiece::None; 
+                }
+                ui.separator(); 
+
+                egui::Grid::new("editor board").num_columns(9).show(ui, |ui| {
+                    for square in Square::all() {
+                        let size = 50.0; 
+                        let mut widget = egui::Button::new(piece_text(self.chesses[square.index()], size * 0.7)); 
+                        if (3..6).contains(&square.col()) && (square.row() < 3 || square.row() > 6) {
+                            widget = widget.fill(Color32::from_gray(140)); 
+                        }
+                        if ui.add_sized([size, size], widget).clicked() {
+                            self.chesses[square.index()] = self.brush; 
+                            self.problems.clear(); 
+                        }
+                        if square.col() == 8 {
+                            ui.end_row(); 
+                        }
+                    }
+                }); 
+                ui.separator(); 
+
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut self.black, false, "红方先走"); 
+                    ui.radio_value(&mut self.black, true, "黑方先走"); 
+                }); 
+                ui.horizontal(|ui| {
+                    if ui.button("清空").clicked() {
+                        self.chesses = [ChessPiece::None; 90]; 
+                        self.problems.clear(); 
+                    }
+                    if ui.button("初始局面").clicked() {
+                        self.chesses = *ChessInfo::new().chesses(); 
+                        self.problems.clear(); 
+                    }
+                    if ui.button("开始对局").clicked() {
+                        let chess = ChessInfo::from_chesses(self.chesses, self.black); 
+                        self.problems = validate(&chess, self.black); 
+                        if self.problems.is_empty() {
+                            result = Some(MyApp::from_position(chess)); 
+                        }
+                    }
+                }); 
+                for problem in self.problems.iter() {
+                    ui.label(RichText::new(problem.to_string()).color(Color32::RED)); 
+                }
+            }); 
+            result 
+        }
+    }
+
+    impl Default for Editor {
+        fn default() -> Self {
+            Self::new() 
+        }
+    }
+
+    fn piece_text(piece: ChessPiece, size: f32) -> RichText {
+        let word = RichText::new(piece.name()).size(size); 
+        match piece {
+            ChessPiece::Chess { chess_type: _, black: true, .. } => word.color(Color32::BLACK), 
+            ChessPiece::Chess { chess_type: _, black: false, .. } => word.color(Color32::from_rgb(200, 50, 30)), 
+            ChessPiece::None => word, 
+        }
+    }
+}
@@ -8,4 +8,20 @@ pub fn setup_fonts(ctx : &egui::Context) {
     fonts.families.entry(egui::FontFamily::Proportional).or_default().insert(0, "font1".to_owned()); 
     fonts.families.entry(egui::FontFamily::Monospace).or_default().push("font1".to_owned()); 
     ctx.set_fonts(fonts); 
-}
\ No newline at end of file
+}
+/// The next number of a splitmix64 sequence, advancing `state`. 
+pub fn splitmix(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15); 
+    let mut z = *state; 
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9); 
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB); 
+    z ^ (z >> 31) 
+}
+
+/// Fisher-Yates shuffle, driven by the splitmix64 `state`. 
+pub fn shuffle<T>(items: &mut [T], state: &mut u64) {
+    for i in (1..items.len()).rev() {
+        let j = (splitmix(state) % (i as u64 + 1)) as usize; 
+        items.swap(i, j); 
+    }
+}
@@ -0,0 +1,145 @@
+//! Checks whether a board could come up in a real game, before it's trusted.
+
+use crate::ChessInfo;
+use crate::square::Square;
+use crate::xiangqi::{ChessPiece, ChessType, Hidden, camp_name, find_jiang, generals_facing, is_checked, possible_square};
+
+/// Something wrong with a position.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Problem {
+    /// The camp has no general.
+    MissingGeneral { black: bool },
+    /// The camp has more chesses of the type than the set holds.
+    TooMany { chess_type: ChessType, black: bool, count: usize },
+    /// The chess can't ever reach `square`, see [`possible_square`].
+    ImpossibleSquare { square: Square, chess_type: ChessType, black: bool },
+    /// The generals face each other on an open file.
+    GeneralsFacing,
+    /// The camp which doesn't move is in check, so its general could be captured.
+    WaitingInCheck { black: bool },
+}
+
+impl std::fmt::Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Problem::MissingGeneral { black } => {
+                write!(f, "{}缺少{}", camp_name(black), ChessType::JIANG.name(black))
+            }
+            Problem::TooMany { chess_type, black, count } => {
+                write!(f, "{}有{}个{}，最多{}个", camp_name(black), count, chess_type.name(black), most(chess_type))
+            }
+            Problem::ImpossibleSquare { square, chess_type, black } => {
+                write!(f, "{}不能在{}", chess_type.name(black), square)
+            }
+            Problem::GeneralsFacing => write!(f, "将帅照面"),
+            Problem::WaitingInCheck { black } => write!(f, "{}不走棋却被将军", camp_name(black)),
+        }
+    }
+}
+
+/// How many chesses of a type a camp owns at most.
+pub fn most(chess_type: ChessType) -> usize {
+    match chess_type {
+        ChessType::JIANG => 1,
+        ChessType::BING => 5,
+        _ => 2,
+    }
+}
+
+/// Every problem of the position with `side_to_move` to move; empty for a sound one.
+///
+/// Revealed chesses of jieqi may stand anywhere, face-down ones count as the type they move as.
+pub fn validate(chess: &ChessInfo, side_to_move: bool) -> Vec<Problem> {
+    let chesses = chess.chesses();
+    let mut result = Vec::new();
+    for black in [false, true] {
+        if find_jiang(chesses, black).is_none() {
+            result.push(Problem::MissingGeneral { black });
+        }
+        for chess_type in ChessType::ALL {
+            let count = chesses.iter()
+                .filter(|piece| matches!(piece, ChessPiece::Chess { chess_type: t, black: b, .. } if *t == chess_type && *b == black))
+                .count();
+            if count > most(chess_type) {
+                result.push(Problem::TooMany { chess_type, black, count });
+            }
+        }
+    }
+    for square in Square::all() {
+        if let ChessPiece::Chess { chess_type, black, hidden } = chesses[square.index()] {
+            if hidden != Hidden::Revealed && !possible_square(chess_type, black, square) {
+                result.push(Problem::ImpossibleSquare { square, chess_type, black });
+            }
+        }
+    }
+    if generals_facing(chesses) {
+        result.push(Problem::GeneralsFacing);
+    } else if is_checked(chesses, !side_to_move) {
+        result.push(Problem::WaitingInCheck { black: !side_to_move });
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn problems(fen: &str) -> Vec<Problem> {
+        let chess = ChessInfo::from_fen(fen).unwrap();
+        validate(&chess, chess.side_to_move())
+    }
+
+    fn square(text: &str) -> Square {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn opening() {
+        assert_eq!(validate(&ChessInfo::new(), false), []);
+        assert_eq!(problems("rnbakabnr/9/1c5c1/p1p1p1p1p/9/9/P1P1P1P1P/1C5C1/9/RNBAKABNR w"), []);
+    }
+
+    #[test]
+    fn generals() {
+        assert_eq!(problems("9/9/9/9/9/9/9/9/9/4K4 w"), [Problem::MissingGeneral { black: true }]);
+        assert_eq!(
+            problems("4k4/9/9/9/9/9/9/9/9/K8 w"),
+            [Problem::ImpossibleSquare { square: square("a0"), chess_type: ChessType::JIANG, black: false }]
+        );
+    }
+
+    #[test]
+    fn too_many() {
+        assert_eq!(
+            problems("4k4/9/9/9/2P6/9/P1P1P1P1P/9/9/4K4 w"),
+            [Problem::TooMany { chess_type: ChessType::BING, black: false, count: 6 }]
+        );
+    }
+
+    #[test]
+    fn impossible_squares() {
+        assert_eq!(
+            problems("3k5/9/9/9/9/9/9/9/4K4/4A4 w"),
+            [Problem::ImpossibleSquare { square: square("e0"), chess_type: ChessType::SHI, black: false }]
+        );
+        assert_eq!(
+            problems("3k5/9/9/9/2B6/9/9/9/9/4K4 w"),
+            [Problem::ImpossibleSquare { square: square("c5"), chess_type: ChessType::XIANG, black: false }]
+        );
+        assert_eq!(
+            problems("3k5/9/9/9/9/9/9/P8/9/4K4 w"),
+            [Problem::ImpossibleSquare { square: square("a2"), chess_type: ChessType::BING, black: false }]
+        );
+    }
+
+    #[test]
+    fn facing_generals() {
+        assert_eq!(problems("4k4/9/9/9/9/9/9/9/9/4K4 w"), [Problem::GeneralsFacing]);
+    }
+
+    #[test]
+    fn waiting_in_check() {
+        assert_eq!(problems("4k4/9/9/9/4R4/9/9/9/9/3K5 w"), [Problem::WaitingInCheck { black: true }]);
+        assert_eq!(problems("4k4/9/9/9/4R4/9/9/9/9/3K5 b"), []);
+    }
+}
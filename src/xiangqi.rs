@@ -1,8 +1,9 @@
-use self::moves::{secure, bing, pao, che, ma, xiang, shi, jiang};
+use crate::bitboard::Board;
+use crate::square::{Move, Square};
 
 /// In this type enum, we just directly use pinyin to describe the type of the chess, in chinese. 
 /// Because this is the chinese chess ) 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum ChessType {
     /// 兵 / 卒
     BING, 
@@ -20,24 +21,236 @@ pub enum ChessType {
     JIANG, 
 }
 
-#[derive(Clone, Copy)]
+impl ChessType {
+    /// Every type, from the general down to the soldier. 
+    pub const ALL: [ChessType; 7] = [
+        ChessType::JIANG, ChessType::SHI, ChessType::XIANG, ChessType::MA, 
+        ChessType::CHE, ChessType::PAO, ChessType::BING, 
+    ]; 
+
+    /// The character written on the chess of the given camp. 
+    pub fn name(self, black: bool) -> &'static str {
+        match (self, black) {
+            (ChessType::BING, true) => "卒", 
+            (ChessType::BING, false) => "兵", 
+            (ChessType::PAO, true) => "砲", 
+            (ChessType::PAO, false) => "炮", 
+            (ChessType::CHE, true) => "車", 
+            (ChessType::CHE, false) => "车", 
+            (ChessType::MA, true) => "馬", 
+            (ChessType::MA, false) => "马", 
+            (ChessType::XIANG, true) => "象", 
+            (ChessType::XIANG, false) => "相", 
+            (ChessType::SHI, true) => "仕", 
+            (ChessType::SHI, false) => "士", 
+            (ChessType::JIANG, true) => "将", 
+            (ChessType::JIANG, false) => "帅", 
+        }
+    }
+}
+
+/// The name of a camp as shown to the players, 红方 or 黑方. 
+pub fn camp_name(black: bool) -> &'static str {
+    match black {
+        true => "黑方", 
+        false => "红方", 
+    }
+}
+
+/// Whether a chess is face-down, for jieqi (揭棋). 
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Hidden {
+    /// An ordinary chess, which was never face-down. 
+    Never, 
+    /// A face-down chess of the given real type. It moves as its `chess_type`, the type 
+    /// which normally starts on its square, and stays there until its first move. 
+    FaceDown(ChessType), 
+    /// Turned face up by its first move. Advisors and elephants are then free to leave 
+    /// the palace and cross the river. 
+    Revealed, 
+}
+
+#[derive(Clone, Copy, Debug)]
 pub enum ChessPiece {
     /// There isn't any chess on this chess. 
     None, 
-    /// A chess piece with two info: chess type and camp 
+    /// A chess piece with three info: chess type, camp and hidden state 
     Chess {
         chess_type : ChessType, 
         black: bool, 
+        hidden: Hidden, 
     }
 }
 
-pub fn calculate_operators(chesses: &[ChessPiece; 90], index: usize) -> Vec<usize> {
-    secure(index);
-    let piece = chesses[index]; 
+impl ChessPiece {
+    /// The character drawn for the chess; a face-down one shows 暗 only. 
+    pub fn name(self) -> &'static str {
+        match self {
+            ChessPiece::None => "", 
+            ChessPiece::Chess { chess_type: _, black: _, hidden: Hidden::FaceDown(_) } => "暗", 
+            ChessPiece::Chess { chess_type, black, .. } => chess_type.name(black), 
+        }
+    }
+
+    /// The chess after its first move: a face-down one turns face up as its real type. 
+    pub fn revealed(self) -> ChessPiece {
+        match self {
+            ChessPiece::Chess { black, hidden: Hidden::FaceDown(chess_type), .. } => {
+                ChessPiece::Chess { chess_type, black, hidden: Hidden::Revealed }
+            }
+            piece => piece, 
+        }
+    }
+}
+
+/// Why the rules refuse to work on a board or a position of it. 
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RulesError {
+    /// The index is not on the 9x10 board. 
+    OutOfRange(usize), 
+    /// There isn't any chess on the position. 
+    EmptySquare(Square), 
+    /// The chess can never stand on this position, like an advisor outside the palace. 
+    ImpossibleSquare {
+        square: Square, 
+        chess_type: ChessType, 
+        black: bool, 
+    }, 
+    /// A generator of one chess type was asked for another one. 
+    TypeMismatch {
+        square: Square, 
+        expected: ChessType, 
+        found: ChessType, 
+    }, 
+}
+
+impl std::fmt::Display for RulesError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RulesError::OutOfRange(index) => write!(f, "index {index} is out of the board"), 
+            RulesError::EmptySquare(square) => write!(f, "there isn't any chess on {square}"), 
+            RulesError::ImpossibleSquare { square, chess_type, black } => {
+                write!(f, "{chess_type:?} (black: {black}) can never stand on {square}")
+            }
+            RulesError::TypeMismatch { square, expected, found } => {
+                write!(f, "expect {expected:?} on {square}, found {found:?}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RulesError {}
+
+/// Whether a chess could ever stand on `square` in a real game: the general inside the palace, 
+/// the advisors on its diagonals, the elephants on their seven points of their own side, and 
+/// the soldiers never behind their starting rank, nor off their files before crossing the river. 
+pub fn possible_square(chess_type: ChessType, black: bool, square: Square) -> bool {
+    let (row, col) = (square.relative_row(black), square.col()); 
+    match chess_type {
+        ChessType::JIANG => row <= 2 && (3..=5).contains(&col), 
+        ChessType::SHI => row <= 2 && (3..=5).contains(&col) && (col == 4) == (row == 1), 
+        ChessType::XIANG => matches!((row, col), (0 | 4, 2 | 6) | (2, 0 | 4 | 8)), 
+        ChessType::BING => row >= 5 || (row >= 3 && col % 2 == 0), 
+        ChessType::PAO | ChessType::CHE | ChessType::MA => true, 
+    }
+}
+
+/// The pseudo-legal targets of the chess on `square`, generated on bitboards. 
+/// They are exactly the targets of the per-piece functions in [`moves`]. 
+/// 
+/// The bitboards are built for this one query; code asking about many chesses of one board 
+/// keeps a [`Board`] instead, like [`crate::ChessInfo::board`]. 
+pub fn calculate_operators(chesses: &[ChessPiece; 90], square: Square) -> Result<Vec<Square>, RulesError> {
+    let piece = chesses[square.index()]; 
     match piece {
-        ChessPiece::None => todo!(),
-        ChessPiece::Chess { chess_type, black: _} => {
-            match chess_type {
+        ChessPiece::None => Err(RulesError::EmptySquare(square)),
+        ChessPiece::Chess { chess_type, black, hidden } => {
+            // a revealed chess of jieqi may stand anywhere. 
+            if hidden != Hidden::Revealed && !possible_square(chess_type, black, square) {
+                return Err(RulesError::ImpossibleSquare { square, chess_type, black }); 
+            }
+            Ok(Board::new(chesses).targets(square).iter().collect())
+        }
+    }
+}
+
+/// Find where the general (帅 / 将) of the given camp stands.
+pub fn find_jiang(chesses: &[ChessPiece; 90], black: bool) -> Option<Square> {
+    Square::all().find(|square| match chesses[square.index()] {
+        ChessPiece::Chess { chess_type: ChessType::JIANG, black: chess_camp, .. } => chess_camp == black,
+        _ => false,
+    })
+}
+
+/// Whether the two generals stand on the same file with nothing between them (将帅对脸). 
+///
+/// Such a position is illegal, so no move may produce it. 
+pub fn generals_facing(chesses: &[ChessPiece; 90]) -> bool {
+    let (red, black) = match (find_jiang(chesses, false), find_jiang(chesses, true)) {
+        (Some(red), Some(black)) => (red, black),
+        _ => return false,
+    };
+    if red.col() != black.col() {
+        return false;
+    }
+    let (low, high) = if red < black { (red, black) } else { (black, red) };
+    (low.index() + 9..high.index()).step_by(9).all(|index| matches!(chesses[index], ChessPiece::None))
+}
+
+/// Whether the general of the given camp can be captured by the other camp right now.
+///
+/// A board without that general is never regarded as checked.
+pub fn is_checked(chesses: &[ChessPiece; 90], black: bool) -> bool {
+    Board::new(chesses).is_checked(black)
+}
+
+/// The targets of the chess on `square` which don't leave its own general in check.
+///
+/// Every pseudo-legal target from [`calculate_operators`] is tried on a copy of the bitboards.
+pub fn legal_operators(chesses: &[ChessPiece; 90], square: Square) -> Result<Vec<Square>, RulesError> {
+    // report an empty or impossible square first. 
+    calculate_operators(chesses, square)?;
+    Ok(Board::new(chesses).legal_targets(square).iter().collect())
+}
+
+/// Every legal move of the given camp, on one [`Board`] built for the whole board. 
+/// 
+/// A chess standing on an impossible position can't move at all. 
+pub fn legal_moves(chesses: &[ChessPiece; 90], black: bool) -> Vec<Move> {
+    let board = Board::new(chesses); 
+    let mut result = Vec::new(); 
+    for from in board.camp(black).iter() {
+        for to in board.legal_targets(from).iter() {
+            result.push(Move::new(chesses, from, to)); 
+        }
+    }
+    result 
+}
+
+/// Whether the given camp still has any legal move; a camp without one has lost, 
+/// both by checkmate (绝杀) and by stalemate (困毙). 
+/// 
+/// A chess standing on an impossible position can't move at all. 
+pub fn has_legal_move(board: &Board, black: bool) -> bool {
+    board.camp(black).iter().any(|square| !board.legal_targets(square).is_empty())
+}
+
+/// The per-piece move generators walking the board array by hand. 
+/// [`calculate_operators`] no longer uses them, they are the reference to verify the bitboards against. 
+pub mod moves {
+    use super::{ChessPiece, ChessType, Hidden, RulesError, find_jiang, generals_facing};
+    use crate::square::Square;
+
+    pub fn secure(index: usize) -> Result<Square, RulesError> {
+        Square::try_from(index)
+    }
+
+    /// Dispatch to the generator of the chess on `square`, in the shape of [`super::calculate_operators`]. 
+    pub fn calculate(chesses: &[ChessPiece; 90], square: Square) -> Result<Vec<Square>, RulesError> {
+        let index = square.index(); 
+        let targets = match chesses[index] {
+            ChessPiece::None => return Err(RulesError::EmptySquare(square)), 
+            ChessPiece::Chess { chess_type, black: _, .. } => match chess_type {
                 ChessType::BING => bing(chesses, index), 
                 ChessType::PAO => pao(chesses, index), 
                 ChessType::CHE => che(chesses, index), 
@@ -46,35 +259,56 @@ pub fn calculate_operators(chesses: &[ChessPiece; 90], index: usize) -> Vec<usiz
                 ChessType::SHI => shi(chesses, index), 
                 ChessType::JIANG => jiang(chesses, index), 
             }
-        }
+        }?; 
+        targets.into_iter().map(secure).collect()
     }
-}
 
-mod moves {
-    use super::{ChessPiece, ChessType};
+    fn revealed(chesses: &[ChessPiece; 90], index: usize) -> bool {
+        matches!(chesses[index], ChessPiece::Chess { hidden: Hidden::Revealed, .. })
+    }
 
-    pub fn secure(index: usize) {
-        assert!(index < 90, "Invalid index {index}: out of bounds.");
+    /// The steps of a revealed advisor or elephant of jieqi, free to go anywhere on the board. 
+    /// An elephant step is blocked by a chess on its eye. 
+    fn free_steps(chesses: &[ChessPiece; 90], index: usize, black: bool, distance: i32) -> Result<Vec<usize>, RulesError> {
+        let square = secure(index)?; 
+        let mut result = Vec::new(); 
+        for (d_row, d_col) in [(1, 1), (1, -1), (-1, 1), (-1, -1)] {
+            let nxt = match square.offset(d_row * distance, d_col * distance) {
+                Some(nxt) => nxt.index(), 
+                None => continue, 
+            }; 
+            if distance == 2 && !matches!(chesses[(index + nxt) / 2], ChessPiece::None) {
+                continue; 
+            }
+            match chesses[nxt] {
+                ChessPiece::Chess { chess_type: _, black: chess_camp, .. } if chess_camp == black => (), 
+                _ => {
+                    result.push(nxt); 
+                }
+            }
+        }
+        Ok(result) 
     }
 
-    fn get_black(piece: ChessPiece, expect_type: ChessType) -> bool {
-        match piece {
-            ChessPiece::Chess { chess_type, black } if chess_type == expect_type => {
-                black 
+    fn get_black(chesses: &[ChessPiece; 90], index: usize, expect_type: ChessType) -> Result<bool, RulesError> {
+        let square = secure(index)?; 
+        match chesses[index] {
+            ChessPiece::Chess { chess_type, black, .. } if chess_type == expect_type => {
+                Ok(black) 
             }
-            _ => panic!(), 
+            ChessPiece::Chess { chess_type, black: _, .. } => Err(RulesError::TypeMismatch { square, expected: expect_type, found: chess_type }), 
+            ChessPiece::None => Err(RulesError::EmptySquare(square)), 
         }
     }
 
-    pub fn jiang(chesses: &[ChessPiece; 90], index: usize) -> Vec<usize> {
-        secure(index); 
-        let piece = chesses[index]; 
+    pub fn jiang(chesses: &[ChessPiece; 90], index: usize) -> Result<Vec<usize>, RulesError> {
+        secure(index)?; 
         let (row, col) = (index / 9, index % 9); 
         let mut result = Vec::new(); 
-        let black = get_black(piece, ChessType::JIANG); 
+        let black = get_black(chesses, index, ChessType::JIANG)?; 
         if col > 3 {
             match chesses[row * 9 + col - 1] {
-                ChessPiece::Chess { chess_type: _, black: chess_camp} if chess_camp == black => (), 
+                ChessPiece::Chess { chess_type: _, black: chess_camp, .. } if chess_camp == black => (), 
                 _ => {
                     result.push(row * 9 + col - 1);
                 }
@@ -82,7 +316,7 @@ mod moves {
         }
         if col < 5 {
             match chesses[row * 9 + col + 1] {
-                ChessPiece::Chess { chess_type: _, black: chess_camp} if chess_camp == black => (), 
+                ChessPiece::Chess { chess_type: _, black: chess_camp, .. } if chess_camp == black => (), 
                 _ => {
                     result.push(row * 9 + col + 1);
                 }
@@ -92,7 +326,7 @@ mod moves {
             false => {
                 if row > 0 {
                     match chesses[(row - 1) * 9 + col] {
-                        ChessPiece::Chess { chess_type: _, black: chess_camp} if chess_camp == black => (), 
+                        ChessPiece::Chess { chess_type: _, black: chess_camp, .. } if chess_camp == black => (), 
                         _ => {
                             result.push((row - 1) * 9 + col);
                         }
@@ -100,7 +334,7 @@ mod moves {
                 }
                 if row < 2 { 
                     match chesses[(row + 1) * 9 + col] {
-                        ChessPiece::Chess { chess_type: _, black: chess_camp} if chess_camp == black => (), 
+                        ChessPiece::Chess { chess_type: _, black: chess_camp, .. } if chess_camp == black => (), 
                         _ => {
                             result.push((row + 1) * 9 + col);
                         }
@@ -110,7 +344,7 @@ mod moves {
             true => {
                 if row > 7 {
                     match chesses[(row - 1) * 9 + col] {
-                        ChessPiece::Chess { chess_type: _, black: chess_camp} if chess_camp == black => (), 
+                        ChessPiece::Chess { chess_type: _, black: chess_camp, .. } if chess_camp == black => (), 
                         _ => {
                             result.push((row - 1) * 9 + col);
                         }
@@ -118,7 +352,7 @@ mod moves {
                 }
                 if row < 9 { 
                     match chesses[(row + 1) * 9 + col] {
-                        ChessPiece::Chess { chess_type: _, black: chess_camp} if chess_camp == black => (), 
+                        ChessPiece::Chess { chess_type: _, black: chess_camp, .. } if chess_camp == black => (), 
                         _ => {
                             result.push((row + 1) * 9 + col);
                         }
@@ -126,22 +360,27 @@ mod moves {
                 }
             }
         }
-        result 
+        // the general flies straight down an open file and captures the other one. 
+        if generals_facing(chesses) {
+            if let Some(other) = find_jiang(chesses, !black) {
+                result.push(other.index()); 
+            }
+        }
+        Ok(result) 
     }
 
-    pub fn pao(chesses: &[ChessPiece; 90], index: usize) -> Vec<usize> {
-        secure(index); 
-        let piece = chesses[index]; 
+    pub fn pao(chesses: &[ChessPiece; 90], index: usize) -> Result<Vec<usize>, RulesError> {
+        secure(index)?; 
         let (row, col) = (index / 9, index % 9); 
         let mut result = Vec::new(); 
-        let black = get_black(piece, ChessType::PAO); 
+        let black = get_black(chesses, index, ChessType::PAO)?; 
         let mut row_increase = row + 1; 
         while row_increase < 10 {
             match chesses[row_increase * 9 + col] {
                 ChessPiece::None => {
                     result.push(row_increase * 9 + col);
                 }
-                ChessPiece::Chess { chess_type: _, black: _ } => {
+                ChessPiece::Chess { chess_type: _, black: _, .. } => {
                     break; 
                 }
             }
@@ -153,7 +392,7 @@ mod moves {
             while row_increase < 10 {
                 match chesses[row_increase * 9 + col] {
                     ChessPiece::None => (), 
-                    ChessPiece::Chess { chess_type: _, black: b} => {
+                    ChessPiece::Chess { chess_type: _, black: b, .. } => {
                         if b != black {
                             result.push(row_increase * 9 + col); 
                         }
@@ -170,7 +409,7 @@ mod moves {
                     ChessPiece::None => {
                         result.push(row_decrease * 9 + col);
                     }
-                    ChessPiece::Chess { chess_type: _, black: _ } => {
+                    ChessPiece::Chess { chess_type: _, black: _, .. } => {
                         break; 
                     }
                 }
@@ -184,7 +423,7 @@ mod moves {
                 loop {
                     match chesses[row_decrease * 9 + col] {
                         ChessPiece::None => (), 
-                        ChessPiece::Chess { chess_type: _, black: b} => {
+                        ChessPiece::Chess { chess_type: _, black: b, .. } => {
                             if b != black {
                                 result.push(row_decrease * 9 + col);
                             }
@@ -204,7 +443,7 @@ mod moves {
                 ChessPiece::None => {
                     result.push(row * 9 + col_increase); 
                 }
-                ChessPiece::Chess { chess_type: _, black: _ } => {
+                ChessPiece::Chess { chess_type: _, black: _, .. } => {
                     break; 
                 }
             }
@@ -215,7 +454,7 @@ mod moves {
             while col_increase < 9 {
                 match chesses[row * 9 + col_increase] {
                     ChessPiece::None => (), 
-                    ChessPiece::Chess { chess_type: _, black: b} => {
+                    ChessPiece::Chess { chess_type: _, black: b, .. } => {
                         if b != black {
                             result.push(row * 9 + col_increase); 
                         }
@@ -231,7 +470,7 @@ mod moves {
                 ChessPiece::None => {
                     result.push(row * 9 + col_decrease - 1);
                 }
-                ChessPiece::Chess { chess_type: _, black: _ } => {
+                ChessPiece::Chess { chess_type: _, black: _, .. } => {
                     break; 
                 }
             }
@@ -242,7 +481,7 @@ mod moves {
             while col_decrease > 0 {
                 match chesses[row * 9 + col_decrease - 1] {
                     ChessPiece::None => (), 
-                    ChessPiece::Chess { chess_type: _, black: b} => {
+                    ChessPiece::Chess { chess_type: _, black: b, .. } => {
                         if b != black {
                             result.push(row * 9 + col_decrease - 1); 
                         }
@@ -252,21 +491,20 @@ mod moves {
                 col_decrease -= 1; 
             }
         }
-        result 
+        Ok(result) 
     }
 
-    pub fn bing(chesses: &[ChessPiece; 90], index: usize) -> Vec<usize> {
-        secure(index); 
-        let piece = chesses[index]; 
+    pub fn bing(chesses: &[ChessPiece; 90], index: usize) -> Result<Vec<usize>, RulesError> {
+        secure(index)?; 
         let (row, col) = (index / 9, index % 9); 
         let mut result = Vec::new(); 
-        let black = get_black(piece, ChessType::BING); 
+        let black = get_black(chesses, index, ChessType::BING)?; 
         match black {
             false => {
                 if row < 9 {
                     let nxt = (row + 1) * 9 + col; 
                     match &chesses[nxt] {
-                        ChessPiece::Chess { chess_type: _, black: false } => (), 
+                        ChessPiece::Chess { chess_type: _, black: false, .. } => (), 
                         _ => {
                             result.push(nxt); 
                         }
@@ -276,7 +514,7 @@ mod moves {
                     if col > 0 {
                         let nxt = row * 9 + (col - 1); 
                         match &chesses[nxt] {
-                            ChessPiece::Chess { chess_type: _, black: false } => (), 
+                            ChessPiece::Chess { chess_type: _, black: false, .. } => (), 
                             _ => {
                                 result.push(nxt); 
                             }
@@ -285,7 +523,7 @@ mod moves {
                     if col < 8 {
                         let nxt = row * 9 + (col + 1); 
                         match &chesses[nxt] {
-                            ChessPiece::Chess { chess_type: _, black: false } => (), 
+                            ChessPiece::Chess { chess_type: _, black: false, .. } => (), 
                             _ => {
                                 result.push(nxt); 
                             }
@@ -297,7 +535,7 @@ mod moves {
                 if row > 0 {
                     let nxt = (row - 1) * 9 + col; 
                     match &chesses[nxt] {
-                        ChessPiece::Chess { chess_type: _, black: true } => (), 
+                        ChessPiece::Chess { chess_type: _, black: true, .. } => (), 
                         _ => {
                             result.push(nxt); 
                         }
@@ -307,7 +545,7 @@ mod moves {
                     if col > 0 {
                         let nxt = row * 9 + (col - 1); 
                         match &chesses[nxt] {
-                            ChessPiece::Chess { chess_type: _, black: true } => (), 
+                            ChessPiece::Chess { chess_type: _, black: true, .. } => (), 
                             _ => {
                                 result.push(nxt); 
                             }
@@ -316,7 +554,7 @@ mod moves {
                     if col < 8 {
                         let nxt = row * 9 + (col + 1); 
                         match &chesses[nxt] {
-                            ChessPiece::Chess { chess_type: _, black: true } => (), 
+                            ChessPiece::Chess { chess_type: _, black: true, .. } => (), 
                             _ => {
                                 result.push(nxt); 
                             }
@@ -325,15 +563,17 @@ mod moves {
                 }
             }
         }
-        result 
+        Ok(result) 
     }
 
-    pub fn shi(chesses: &[ChessPiece; 90], index: usize) -> Vec<usize> {
-        secure(index); 
-        let piece = chesses[index]; 
+    pub fn shi(chesses: &[ChessPiece; 90], index: usize) -> Result<Vec<usize>, RulesError> {
+        secure(index)?; 
         let (row, col) = (index / 9, index % 9); 
         let mut result = Vec::new(); 
-        let black = get_black(piece, ChessType::SHI); 
+        let black = get_black(chesses, index, ChessType::SHI)?; 
+        if revealed(chesses, index) {
+            return free_steps(chesses, index, black, 1); 
+        }
         match black {
             false => {
                 // red 
@@ -341,7 +581,7 @@ mod moves {
                     (0, 3) => {
                         let nxt = 1 * 9 + 4; 
                         match chesses[nxt] {
-                            ChessPiece::Chess { chess_type: _, black: chess_camp} if chess_camp == black => (), 
+                            ChessPiece::Chess { chess_type: _, black: chess_camp, .. } if chess_camp == black => (), 
                             _ => {
                                 result.push(nxt); 
                             }
@@ -350,7 +590,7 @@ mod moves {
                     (0, 5) => {
                         let nxt = 1 * 9 + 4; 
                         match chesses[nxt] {
-                            ChessPiece::Chess { chess_type: _, black: chess_camp} if chess_camp == black => (), 
+                            ChessPiece::Chess { chess_type: _, black: chess_camp, .. } if chess_camp == black => (), 
                             _ => {
                                 result.push(nxt); 
                             }
@@ -360,7 +600,7 @@ mod moves {
                         let nxts = [3, 5, 21, 23]; 
                         for nxt in nxts {
                             match chesses[nxt] {
-                                ChessPiece::Chess { chess_type: _, black: chess_camp} if chess_camp == black => (), 
+                                ChessPiece::Chess { chess_type: _, black: chess_camp, .. } if chess_camp == black => (), 
                                 _ => {
                                     result.push(nxt); 
                                 }
@@ -370,7 +610,7 @@ mod moves {
                     (2, 3) => {
                         let nxt = 1 * 9 + 4; 
                         match chesses[nxt] {
-                            ChessPiece::Chess { chess_type: _, black: chess_camp} if chess_camp == black => (), 
+                            ChessPiece::Chess { chess_type: _, black: chess_camp, .. } if chess_camp == black => (), 
                             _ => {
                                 result.push(nxt); 
                             }
@@ -379,13 +619,13 @@ mod moves {
                     (2, 5) => {
                         let nxt = 1 * 9 + 4; 
                         match chesses[nxt] {
-                            ChessPiece::Chess { chess_type: _, black: chess_camp} if chess_camp == black => (), 
+                            ChessPiece::Chess { chess_type: _, black: chess_camp, .. } if chess_camp == black => (), 
                             _ => {
                                 result.push(nxt); 
                             }
                         }
                     }
-                    _ => panic!("Invalid position of SHI: {index}"), 
+                    _ => return Err(RulesError::ImpossibleSquare { square: secure(index)?, chess_type: ChessType::SHI, black }), 
                 }
             }
             true => {
@@ -394,7 +634,7 @@ mod moves {
                     (9, 3) => {
                         let nxt = 8 * 9 + 4; 
                         match chesses[nxt] {
-                            ChessPiece::Chess { chess_type: _, black: chess_camp} if chess_camp == black => (), 
+                            ChessPiece::Chess { chess_type: _, black: chess_camp, .. } if chess_camp == black => (), 
                             _ => {
                                 result.push(nxt); 
                             }
@@ -403,7 +643,7 @@ mod moves {
                     (9, 5) => {
                         let nxt = 8 * 9 + 4; 
                         match chesses[nxt] {
-                            ChessPiece::Chess { chess_type: _, black: chess_camp} if chess_camp == black => (), 
+                            ChessPiece::Chess { chess_type: _, black: chess_camp, .. } if chess_camp == black => (), 
                             _ => {
                                 result.push(nxt); 
                             }
@@ -413,7 +653,7 @@ mod moves {
                         let nxts = [84, 86, 66, 68]; 
                         for nxt in nxts {
                             match chesses[nxt] {
-                                ChessPiece::Chess { chess_type: _, black: chess_camp} if chess_camp == black => (), 
+                                ChessPiece::Chess { chess_type: _, black: chess_camp, .. } if chess_camp == black => (), 
                                 _ => {
                                     result.push(nxt); 
                                 }
@@ -423,7 +663,7 @@ mod moves {
                     (7, 3) => {
                         let nxt = 8 * 9 + 4; 
                         match chesses[nxt] {
-                            ChessPiece::Chess { chess_type: _, black: chess_camp} if chess_camp == black => (), 
+                            ChessPiece::Chess { chess_type: _, black: chess_camp, .. } if chess_camp == black => (), 
                             _ => {
                                 result.push(nxt); 
                             }
@@ -432,31 +672,30 @@ mod moves {
                     (7, 5) => {
                         let nxt = 8 * 9 + 4; 
                         match chesses[nxt] {
-                            ChessPiece::Chess { chess_type: _, black: chess_camp} if chess_camp == black => (), 
+                            ChessPiece::Chess { chess_type: _, black: chess_camp, .. } if chess_camp == black => (), 
                             _ => {
                                 result.push(nxt); 
                             }
                         }
                     }
-                    _ => panic!("Invalid position of SHI"), 
+                    _ => return Err(RulesError::ImpossibleSquare { square: secure(index)?, chess_type: ChessType::SHI, black }), 
                 }
             }
         }
-        result 
+        Ok(result) 
     }
 
-    pub fn ma(chesses: &[ChessPiece; 90], index: usize) -> Vec<usize> {
-        secure(index); 
-        let piece = chesses[index]; 
+    pub fn ma(chesses: &[ChessPiece; 90], index: usize) -> Result<Vec<usize>, RulesError> {
+        secure(index)?; 
         let (row, col) = (index / 9, index % 9); 
         let mut result = Vec::new(); 
-        let black = get_black(piece, ChessType::MA); 
+        let black = get_black(chesses, index, ChessType::MA)?; 
         if row < 8 {
             if let ChessPiece::None = chesses[(row + 1 ) * 9 + col] {
                 if col > 0 {
                     let chess_camp = black; 
                     match chesses[(row + 2) * 9 + (col - 1)] {
-                        ChessPiece::Chess { chess_type: _, black} if black == chess_camp => (), 
+                        ChessPiece::Chess { chess_type: _, black, .. } if black == chess_camp => (), 
                         _ => {
                             result.push((row + 2 ) * 9 + (col - 1)); 
                         }
@@ -465,7 +704,7 @@ mod moves {
                 if col < 8 {
                     let chess_camp = black; 
                     match chesses[(row + 2) * 9 + (col + 1)] {
-                        ChessPiece::Chess { chess_type: _, black} if black == chess_camp => (), 
+                        ChessPiece::Chess { chess_type: _, black, .. } if black == chess_camp => (), 
                         _ => {
                             result.push((row + 2 ) * 9 + (col + 1)); 
                         }
@@ -478,7 +717,7 @@ mod moves {
                 if col > 0 {
                     let chess_camp = black; 
                     match chesses[(row - 2) * 9 + (col - 1)] {
-                        ChessPiece::Chess { chess_type: _, black} if black == chess_camp => (), 
+                        ChessPiece::Chess { chess_type: _, black, .. } if black == chess_camp => (), 
                         _ => {
                             result.push((row - 2 ) * 9 + (col - 1)); 
                         }
@@ -487,7 +726,7 @@ mod moves {
                 if col < 8 {
                     let chess_camp = black; 
                     match chesses[(row - 2) * 9 + (col + 1)] {
-                        ChessPiece::Chess { chess_type: _, black} if black == chess_camp => (), 
+                        ChessPiece::Chess { chess_type: _, black, .. } if black == chess_camp => (), 
                         _ => {
                             result.push((row - 2 ) * 9 + (col + 1)); 
                         }
@@ -500,7 +739,7 @@ mod moves {
                 if row > 0 {
                     let chess_camp = black; 
                     match chesses[(row - 1) * 9 + (col - 2)] {
-                        ChessPiece::Chess { chess_type: _, black} if black == chess_camp => (), 
+                        ChessPiece::Chess { chess_type: _, black, .. } if black == chess_camp => (), 
                         _ => {
                             result.push((row - 1) * 9 + (col - 2)); 
                         }
@@ -509,7 +748,7 @@ mod moves {
                 if row < 9 { 
                     let chess_camp = black; 
                     match chesses[(row + 1) * 9 + (col - 2)] {
-                        ChessPiece::Chess { chess_type: _, black} if black == chess_camp => (), 
+                        ChessPiece::Chess { chess_type: _, black, .. } if black == chess_camp => (), 
                         _ => {
                             result.push((row + 1) * 9 + (col - 2)); 
                         }
@@ -522,7 +761,7 @@ mod moves {
                 if row > 0 {
                     let chess_camp = black; 
                     match chesses[(row - 1) * 9 + (col + 2)] {
-                        ChessPiece::Chess { chess_type: _, black} if black == chess_camp => (), 
+                        ChessPiece::Chess { chess_type: _, black, .. } if black == chess_camp => (), 
                         _ => {
                             result.push((row - 1) * 9 + (col + 2)); 
                         }
@@ -531,7 +770,7 @@ mod moves {
                 if row < 9 { 
                     let chess_camp = black; 
                     match chesses[(row + 1) * 9 + (col + 2)] {
-                        ChessPiece::Chess { chess_type: _, black} if black == chess_camp => (), 
+                        ChessPiece::Chess { chess_type: _, black, .. } if black == chess_camp => (), 
                         _ => {
                             result.push((row + 1) * 9 + (col + 2)); 
                         }
@@ -539,22 +778,21 @@ mod moves {
                 }
             }
         }
-        result 
+        Ok(result) 
     }
     
-    pub fn che(chesses: &[ChessPiece; 90], index: usize ) -> Vec<usize> {
-        secure(index); 
+    pub fn che(chesses: &[ChessPiece; 90], index: usize) -> Result<Vec<usize>, RulesError> {
+        secure(index)?; 
         let (row, col) = (index / 9, index % 9); 
         let mut result = Vec::new(); 
-        let piece = chesses[index]; 
-        let black = get_black(piece, ChessType::CHE); 
+        let black = get_black(chesses, index, ChessType::CHE)?; 
         let mut row_increase = row + 1; 
         while row_increase < 10 {
             match chesses[row_increase * 9 + col] {
                 ChessPiece::None => {
                     result.push(row_increase * 9 + col);
                 }
-                ChessPiece::Chess { chess_type: _, black: chess_black} => {
+                ChessPiece::Chess { chess_type: _, black: chess_black, .. } => {
                     if chess_black != black {
                         result.push(row_increase * 9 + col);
                     } 
@@ -570,7 +808,7 @@ mod moves {
                     ChessPiece::None => {
                         result.push(row_decrease * 9 + col);
                     }
-                    ChessPiece::Chess { chess_type: _, black: chess_black} => {
+                    ChessPiece::Chess { chess_type: _, black: chess_black, .. } => {
                         if chess_black != black {
                             result.push(row_decrease * 9 + col);
                         } 
@@ -589,7 +827,7 @@ mod moves {
                 ChessPiece::None => {
                     result.push(row * 9 + col_increase); 
                 }
-                ChessPiece::Chess { chess_type: _, black: chess_black} => {
+                ChessPiece::Chess { chess_type: _, black: chess_black, .. } => {
                     if chess_black != black {
                         result.push(row * 9 + col_increase);
                     }
@@ -604,7 +842,7 @@ mod moves {
                 ChessPiece::None => {
                     result.push(row * 9 + col_decrease - 1);
                 }
-                ChessPiece::Chess { chess_type: _, black: chess_black} => {
+                ChessPiece::Chess { chess_type: _, black: chess_black, .. } => {
                     if black != chess_black {
                         result.push(row * 9 + col_decrease - 1); 
                     }
@@ -613,17 +851,20 @@ mod moves {
             }
             col_decrease -= 1; 
         }
-        result 
+        Ok(result) 
     }
 
-    pub fn xiang(chesses: &[ChessPiece; 90], index: usize ) -> Vec<usize> {
-        secure(index); 
+    pub fn xiang(chesses: &[ChessPiece; 90], index: usize) -> Result<Vec<usize>, RulesError> {
+        secure(index)?; 
         let (row, col) = (index / 9, index % 9); 
         let mut tmp = Vec::new(); 
         let mut result = Vec::new(); 
         let piece = chesses[index]; 
         match piece {
-            ChessPiece::Chess { chess_type: ChessType::XIANG,  black } => {
+            ChessPiece::Chess { chess_type: ChessType::XIANG,  black, hidden: Hidden::Revealed } => {
+                free_steps(chesses, index, black, 2) 
+            }
+            ChessPiece::Chess { chess_type: ChessType::XIANG,  black, .. } => {
                 if col + 2 < 9 {
                     match black {
                         false => {
@@ -682,15 +923,16 @@ mod moves {
                 }
                 for i in tmp {
                     match chesses[i] {
-                        ChessPiece::Chess { chess_type: _, black: c} if c == black => (),
+                        ChessPiece::Chess { chess_type: _, black: c, .. } if c == black => (),
                         _ => {
                             result.push(i); 
                         }
                     }
                 }
-                result 
+                Ok(result) 
             }
-            _ => panic!(), 
+            ChessPiece::Chess { chess_type, black: _, .. } => Err(RulesError::TypeMismatch { square: secure(index)?, expected: ChessType::XIANG, found: chess_type }), 
+            ChessPiece::None => Err(RulesError::EmptySquare(secure(index)?)), 
         }
     }
 }
@@ -0,0 +1,61 @@
+//! Zobrist keys of the board, to recognize a repeated position cheaply. 
+
+use crate::square::Square;
+use crate::xiangqi::{ChessPiece, ChessType, Hidden};
+
+/// 7 chess types with 2 camps, then the face-down chesses of the 2 camps, on 90 positions. 
+const KEYS: [[u64; 90]; 16] = {
+    let mut keys = [[0; 90]; 16]; 
+    // splitmix64, with a fixed seed so the hash is stable between runs. 
+    let mut state: u64 = 0x9E37_79B9_7F4A_7C15; 
+    let mut kind = 0; 
+    while kind < 16 {
+        let mut index = 0; 
+        while index < 90 {
+            state = state.wrapping_add(0x9E37_79B9_7F4A_7C15); 
+            let mut z = state; 
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9); 
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB); 
+            keys[kind][index] = z ^ (z >> 31); 
+            index += 1; 
+        }
+        kind += 1; 
+    }
+    keys 
+}; 
+
+/// Toggled into the hash while black is to move; any fixed odd constant apart from the keys will do. 
+const SIDE: u64 = 0xD6E8_FEB8_6659_FD93; 
+
+fn kind(chess_type: ChessType, black: bool) -> usize {
+    let t = match chess_type {
+        ChessType::BING => 0, 
+        ChessType::PAO => 1, 
+        ChessType::CHE => 2, 
+        ChessType::MA => 3, 
+        ChessType::XIANG => 4, 
+        ChessType::SHI => 5, 
+        ChessType::JIANG => 6, 
+    }; 
+    t * 2 + black as usize 
+}
+
+/// The key of a single chess standing on `square`; an empty square contributes nothing. 
+pub fn key(piece: ChessPiece, square: Square) -> u64 {
+    match piece {
+        ChessPiece::None => 0, 
+        // a face-down chess is known only by its camp. 
+        ChessPiece::Chess { chess_type: _, black, hidden: Hidden::FaceDown(_) } => KEYS[14 + black as usize][square.index()], 
+        ChessPiece::Chess { chess_type, black, .. } => KEYS[kind(chess_type, black)][square.index()], 
+    }
+}
+
+/// The key of the camp to move; red to move contributes nothing. 
+pub fn side(black: bool) -> u64 {
+    if black { SIDE } else { 0 }
+}
+
+/// Hash a whole board from scratch. 
+pub fn hash(chesses: &[ChessPiece; 90]) -> u64 {
+    Square::all().fold(0, |h, square| h ^ key(chesses[square.index()], square))
+}